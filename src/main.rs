@@ -1,13 +1,55 @@
-use std::{error::Error, fs, io};
-use reqwest::{header::USER_AGENT, blocking::Client};
-use serde::Deserialize;
-use clap::{Parser, Subcommand};
+use std::{cmp::Ordering, collections::{HashMap, HashSet}, env, error::Error, fmt, fs, io::{self, IsTerminal, Read, Write}, path::{Component, Path, PathBuf}, process, sync::{Mutex, OnceLock}, thread, time::Duration};
+use reqwest::{header::{ACCEPT_RANGES, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, RANGE, USER_AGENT}, blocking::{Client, RequestBuilder, Response}, redirect::Policy, NoProxy, Proxy, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
 use whoami::Platform;
+use zip::ZipArchive;
+use rand::RngExt;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 
 #[derive(Parser)]
+#[command(version)]
 struct Cli {
     #[command(subcommand)]
-    command: Option<Commands>
+    command: Option<Commands>,
+    #[arg(long, global=true, help="Proxy to send every request through, overrides the HTTP_PROXY/HTTPS_PROXY env vars")]
+    proxy: Option<String>,
+    #[arg(long, global=true, env="RUSTDRYNTH_CONFIG", help="Config file to use instead of config.toml in the config dir, e.g. one kept next to a project")]
+    config: Option<PathBuf>,
+    #[arg(long="no-version-check", global=true, help="Don't check the game version against Modrinth's list of known versions")]
+    no_version_check: bool,
+    #[arg(long, global=true, value_enum, env="RUSTDRYNTH_FORMAT", default_value_t=OutputFormat::Text, help="The output format, errors are also printed as JSON on stderr in json mode, toml is supported by info, list and dependencies")]
+    format: OutputFormat,
+    #[arg(long, global=true, help="Print more details about what's being done")]
+    verbose: bool,
+    #[arg(long, global=true, help="Indent the JSON output so it's easier to read")]
+    pretty: bool,
+    #[arg(long, global=true, help="Only print what's essential and never ask for confirmation")]
+    quiet: bool,
+    #[arg(long, global=true, help="Print the url of every request sent to the API")]
+    explain: bool,
+    #[arg(long="api-url", global=true, env="RUSTDRYNTH_API_URL", default_value=DEFAULT_API_URL, help="Base url of the Modrinth API, e.g. to use the staging one")]
+    api_url: String,
+    #[arg(long="user-agent", global=true, env="RUSTDRYNTH_UA", default_value=DEFAULT_USER_AGENT, help="User agent sent with every request")]
+    user_agent: String,
+    #[arg(long, global=true, env="MODRINTH_TOKEN", hide_env_values=true, help="Modrinth personal access token sent to the API")]
+    token: Option<String>,
+    #[arg(long, global=true, default_value_t=DEFAULT_RETRIES, help="How many times to retry a request that failed because of the connection or the server, 0 to fail right away")]
+    retries: u32,
+    #[arg(long="retry-delay", global=true, default_value_t=DEFAULT_RETRY_DELAY, help="Seconds to wait between retries")]
+    retry_delay: u64,
+    #[arg(long="show-ratelimit", global=true, help="Print the remaining Modrinth rate limit after every request")]
+    show_ratelimit: bool,
+    #[arg(long, global=true, help="Transliterate titles and descriptions to ASCII, for consoles that can't show unicode")]
+    ascii: bool,
+    #[arg(long, global=true, value_enum, default_value_t=ColorMode::Auto, help="Decorate the text output, e.g. with an icon before project types, auto only does it in a terminal without NO_COLOR set")]
+    color: ColorMode,
+    #[arg(long="no-progress", global=true, help="Don't draw progress bars for downloads, they're also left out with --quiet or when the output isn't a terminal")]
+    no_progress: bool,
+    #[arg(short, long, global=true, default_value_t=DEFAULT_JOBS, value_parser=clap::value_parser!(u64).range(1..), help="How many files to download at once")]
+    jobs: u64,
 }
 
 #[derive(Subcommand)]
@@ -18,291 +60,4307 @@ enum Commands {
         #[arg(short, long, help="Categories like \"optimization\", the modloader also goes here")]
         categories: Option<Vec<String>>,
         #[arg(short='v', long="gameversion", help="The Minecraft version to search mods for")]
-        game_version: String
+        game_version: Option<String>,
+        #[arg(long="min-version", conflicts_with="game_version", help="The oldest Minecraft version the mods may support, e.g. \"1.19\"")]
+        min_version: Option<String>,
+        #[arg(long="max-version", conflicts_with="game_version", help="The newest Minecraft version the mods may support, e.g. \"1.21\"")]
+        max_version: Option<String>,
+        #[arg(long="count-only", help="Only print how many mods match the query")]
+        count_only: bool,
+        #[arg(long, help="Print a single line per mod")]
+        compact: bool,
+        #[arg(long, default_value_t=DEFAULT_SEARCH_LIMIT, help="How many mods to list, at most 100")]
+        limit: usize,
+        #[arg(long="max-results", conflicts_with="limit", help="Page through the results until this many mods are gathered, past the 100 of --limit")]
+        max_results: Option<usize>,
+        #[arg(long="description-length", help="Cut the descriptions down to this many characters")]
+        description_length: Option<usize>,
+        #[arg(long, value_enum, default_value_t=SearchSort::Relevance, help="How Modrinth should order the results")]
+        sort: SearchSort,
+        #[arg(long="secondary-sort", help="Breaks ties of the --sort order, e.g. mods with the same downloads; needs a --sort other than relevance")]
+        secondary_sort: Option<SearchSort>,
+        #[arg(long="compatible-with-installed", help="Leave out mods that are incompatible with the installed ones, or that these are incompatible with")]
+        compatible_with_installed: bool,
+        #[arg(long="mcdir", help="Use the .minecraft\\mods folder as the installed mods for --compatible-with-installed")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long="type", value_delimiter=',', default_value="mod", help="The project types to search, e.g. \"mod,shader\"")]
+        project_types: Vec<String>,
+        #[arg(long="updated-since", value_parser=parse_since, help="Only list mods updated after this date, e.g. \"2024-05-01\" or \"30d\"")]
+        updated_since: Option<DateTime<Utc>>,
+        #[arg(long="raw-facet", value_parser=parse_raw_facet, help="Extra facets in Modrinth's own syntax, e.g. '[[\"categories:fabric\"],[\"downloads>1000\"]]', AND'ed with the other filters")]
+        raw_facet: Option<RawFacet>,
+        #[arg(long="dedicated-server", help="Only list projects that are required on the server and not required on the client, for headless servers")]
+        dedicated_server: bool,
     },
     Download {
-        #[arg(short, long, help="The project to download, can be a slug, e.g. \"sodium\", or a id, e.g. \"AABBCC\"")]
-        project: String,
-        #[arg(short = 'v', long, help="The targeted Minecraft version for the downloaded mod")]
+        #[arg(short, long, required_unless_present="sha512", help="The project to download, can be a slug, e.g. \"sodium\", or a id, e.g. \"AABBCC\"")]
+        project: Option<String>,
+        #[arg(long, conflicts_with_all=["project", "with_deps", "filename"], help="Download the exact file with this sha512 hash, e.g. one from a modpack manifest")]
+        sha512: Option<String>,
+        #[arg(short = 'v', long, default_value=ANY_GAME_VERSION, help="The targeted Minecraft version for the downloaded mod, \"any\" or leaving it out picks the newest one regardless")]
         game_version: String,
-        #[arg(short, long, help="The modloader for the mod")]
-        loader: String,
+        #[arg(short, long, required_unless_present="sha512", help="The modloader for the mod")]
+        loader: Option<String>,
         #[arg(long="mcdir", help="Use if you want to install the mod in the .minecraft\\mods folder")]
-        minecraft_dir: bool
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long="with-deps", help="Also download the required dependencies of the mod")]
+        with_deps: bool,
+        #[arg(long="no-deps", conflicts_with="with_deps", help="Never download anything but the mod itself")]
+        no_deps: bool,
+        #[arg(long, help="Download nothing if any required dependency can't be resolved")]
+        strict: bool,
+        #[arg(long="missing-only", requires="with_deps", help="Only download the dependencies that aren't installed in the target folder yet")]
+        missing_only: bool,
+        #[arg(long="output-dir", help="Directory to save every downloaded file to, it's created if needed and takes priority over --mcdir")]
+        output_dir: Option<PathBuf>,
+        #[arg(long="featured-only", help="Only pick versions the author marked as featured, falling back to the latest one if none are")]
+        featured_only: bool,
+        #[arg(long, value_enum, default_value_t=VersionSelect::Newest, help="Which matching version to pick: the newest, the one supporting the most game versions, or the newest featured one")]
+        select: VersionSelect,
+        #[arg(long, help="Download the file with exactly this name, e.g. one listed in a modpack manifest")]
+        filename: Option<String>,
+        #[arg(long="ext", help="Pick the version's file with this extension, e.g. \"jar\" or \"zip\", instead of the primary one")]
+        extension: Option<String>,
+        #[arg(long="sort-files", help="Pick the version's file by size or name instead of the primary one")]
+        sort_files: Option<FileSort>,
+        #[arg(long, value_enum, default_value_t=Classifier::Main, help="Download the sources or javadoc jar instead of the mod itself, when the version ships one")]
+        classifier: Classifier,
+        #[arg(long="filename-template", help="Save the files under this name instead of the one on Modrinth, e.g. \"{slug}-{version}.jar\"")]
+        filename_template: Option<String>,
+        #[arg(short, long, help="Download several files without asking for confirmation")]
+        yes: bool,
+        #[arg(long="dry-run", help="Only show what would be downloaded")]
+        dry_run: bool,
+        #[arg(long="organize-by", help="Put the files in a subfolder named after the loader or the game version")]
+        organize_by: Option<OrganizeBy>,
+        #[arg(long="head-check", help="Check that every file is reachable and show its size before downloading anything")]
+        head_check: bool,
+        #[arg(long="ignore-game-version", help="Pick the version by loader alone, for mods known to work on a game version they don't list")]
+        ignore_game_version: bool,
+        #[arg(long="no-verify", help="Keep downloaded jars and zips even if they don't start like a zip file")]
+        no_verify: bool,
     },
     Info {
         #[arg(short, long, help="The project to get the desc of, can be a slug or an id")]
         project: String,
+        #[arg(long, help="List the project's gallery images instead of its description")]
+        gallery: bool,
+        #[arg(long="summary-only", conflicts_with="gallery", help="Only print the title, type, categories, downloads and the one-line summary")]
+        summary_only: bool,
+    },
+    Versions {
+        #[arg(short, long, help="The project to list the versions of, can be a slug or an id")]
+        project: String,
+        #[arg(short = 'v', long, default_value=ANY_GAME_VERSION, help="Only list the versions for this Minecraft version, \"any\" or leaving it out lists them all")]
+        game_version: String,
+        #[arg(short, long, help="Only list the versions for this loader")]
+        loader: Option<String>,
+        #[arg(long, help="Only list the versions whose name or number contains this, e.g. \"1.20\"")]
+        filter: Option<String>,
     },
     Dependencies {
         #[arg(short, long, help="The targeted project for getting the dependencies")]
         project: String,
-        #[arg(short = 'v', long, help="The Minecraft version of the targeted mod")]
+        #[arg(short = 'v', long, default_value=ANY_GAME_VERSION, help="The Minecraft version of the targeted mod, \"any\" or leaving it out picks the newest one regardless")]
         game_version: String,
         #[arg(short, long, help="The loader of the targeted mod")]
         loader: String,
+        #[arg(long="sort-by", help="Sort the dependencies alphabetically by name, or group them by dependency type")]
+        sort_by: Option<DependencySort>,
+        #[arg(long="no-sort", conflicts_with="sort_by", help="Keep the order the API returned the dependencies in")]
+        no_sort: bool,
+        #[arg(long="export-graph", help="Print the whole dependency tree as a Graphviz DOT or JSON graph instead")]
+        export_graph: Option<GraphFormat>,
+        #[arg(long="missing-only", help="Only list the required dependencies that aren't installed")]
+        missing_only: bool,
+        #[arg(long="mcdir", help="Use the .minecraft\\mods folder as the installed mods for --missing-only")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long="include-external-deps", help="Also list the dependencies that aren't hosted on Modrinth")]
+        include_external_deps: bool,
+    },
+    Add {
+        #[arg(short, long, help="What to search for, an exact slug is picked directly")]
+        query: String,
+        #[arg(short = 'v', long, default_value=ANY_GAME_VERSION, help="The targeted Minecraft version for the mod, \"any\" or leaving it out picks the newest one regardless")]
+        game_version: String,
+        #[arg(short, long, help="The modloader for the mod")]
+        loader: String,
+        #[arg(long="mcdir", help="Use if you want to install the mod in the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(short, long, help="Install the picked mod without asking for confirmation")]
+        yes: bool,
+        #[arg(long="no-deps", help="Don't install the dependencies of the picked mod")]
+        no_deps: bool,
+        #[arg(long, help="Install nothing if any required dependency can't be resolved")]
+        strict: bool,
+        #[arg(long="featured-only", help="Only pick versions the author marked as featured, falling back to the latest one if none are")]
+        featured_only: bool,
+        #[arg(long, value_enum, default_value_t=VersionSelect::Newest, help="Which matching version to pick: the newest, the one supporting the most game versions, or the newest featured one")]
+        select: VersionSelect,
+        #[arg(long="dry-run", help="Only show what would be downloaded")]
+        dry_run: bool,
+        #[arg(long="ext", help="Pick the version's file with this extension, e.g. \"jar\" or \"zip\", instead of the primary one")]
+        extension: Option<String>,
+        #[arg(long="sort-files", help="Pick the version's file by size or name instead of the primary one")]
+        sort_files: Option<FileSort>,
+    },
+    Check {
+        #[arg(help="The mods of the set, as slugs or ids")]
+        projects: Vec<String>,
+        #[arg(short, long, help="A file listing one mod per line, lines starting with # are skipped")]
+        file: Option<PathBuf>,
+        #[arg(short = 'v', long, default_value=ANY_GAME_VERSION, help="The Minecraft version of the set")]
+        game_version: String,
+        #[arg(short, long, help="The modloader of the set")]
+        loader: String,
+    },
+    #[command(name="gameversions")]
+    GameVersions {
+        #[arg(long="allow-snapshots", help="Also list snapshot versions like \"23w31a\"")]
+        allow_snapshots: bool,
+    },
+    Remove {
+        #[arg(short, long, help="The project to remove, can be a slug or an id")]
+        project: String,
+        #[arg(long="mcdir", help="Use if you want to remove the mod from the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long, help="Remove the mod even if other installed mods require it")]
+        force: bool,
+    },
+    Update {
+        #[arg(short = 'v', long, help="The Minecraft version to update the mods to")]
+        game_version: String,
+        #[arg(short, long, help="The modloader of the mods")]
+        loader: String,
+        #[arg(long="mcdir", help="Use if you want to update the mods of the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long="keep-old", help="Move the replaced jars to a .old folder instead of deleting them")]
+        keep_old: bool,
+        #[arg(short, long, help="Update the mods without asking for confirmation")]
+        yes: bool,
+    },
+    Prune {
+        #[arg(short = 'v', long, help="The Minecraft version the mods have to support")]
+        game_version: String,
+        #[arg(short, long, help="The modloader of the mods")]
+        loader: String,
+        #[arg(long="mcdir", help="Use if you want to prune the mods of the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long="dry-run", help="Only show what would be pruned")]
+        dry_run: bool,
+        #[arg(long="keep-old", help="Move the pruned jars to a .old folder instead of deleting them")]
+        keep_old: bool,
+    },
+    Watch {
+        #[arg(short = 'v', long, help="The Minecraft version to look for updates for")]
+        game_version: String,
+        #[arg(short, long, help="The modloader of the mods")]
+        loader: String,
+        #[arg(long="mcdir", help="Use if you want to watch the mods of the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long, default_value_t=DEFAULT_WATCH_INTERVAL, value_parser=clap::value_parser!(u64).range(1..), help="Minutes to wait between checks")]
+        interval: u64,
+        #[arg(long="auto-update", help="Download the updates as soon as they're found")]
+        auto_update: bool,
+        #[arg(long, help="Check a single time and exit")]
+        once: bool,
+    },
+    List {
+        #[arg(long="mcdir", help="Use if you want to list the mods of the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+    },
+    Verify {
+        #[arg(long="mcdir", help="Use if you want to verify the mods of the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+    },
+    #[command(name="whatis")]
+    WhatIs {
+        #[arg(help="Path to the jar to identify")]
+        file: PathBuf,
+    },
+    Collection {
+        #[arg(help="The id of the Modrinth collection to install")]
+        collection: String,
+        #[arg(short = 'v', long, default_value=ANY_GAME_VERSION, help="The targeted Minecraft version for the mods, \"any\" or leaving it out picks the newest one regardless")]
+        game_version: String,
+        #[arg(short, long, help="The modloader for the mods")]
+        loader: String,
+        #[arg(long="mcdir", help="Use if you want to install the mods in the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long="no-deps", help="Don't install the dependencies of the collection's mods")]
+        no_deps: bool,
+        #[arg(long, value_delimiter=',', help="Projects to leave out, by slug or id, e.g. \"sodium,iris\", their dependencies are still installed if something else needs them")]
+        exclude: Vec<String>,
+        #[arg(short, long, help="Download the files without asking for confirmation")]
+        yes: bool,
+        #[arg(long="dry-run", help="Only show what would be downloaded")]
+        dry_run: bool,
+    },
+    Modpack {
+        #[arg(help="Path to the .mrpack file to install")]
+        file: PathBuf,
+        #[arg(long="output-dir", help="The instance directory to install the modpack into, the current one by default")]
+        output_dir: Option<PathBuf>,
+        #[arg(long, value_delimiter=',', help="Projects to leave out, by slug, id or file name, e.g. \"sodium,iris\"")]
+        exclude: Vec<String>,
+        #[arg(short, long, help="Download the files without asking for confirmation")]
+        yes: bool,
+    },
+    Diff {
+        #[arg(help="The old mod set, a .mrpack, its modrinth.index.json or a mods folder")]
+        old: PathBuf,
+        #[arg(help="The new mod set, a .mrpack, its modrinth.index.json or a mods folder")]
+        new: PathBuf,
+    },
+    Backup {
+        #[arg(long="mcdir", help="Use if you want to back up the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long="backup-dir", help="Where to save the backup, overrides backup_dir of the config file")]
+        backup_dir: Option<PathBuf>,
+    },
+    Restore {
+        #[arg(help="The backup to restore, a path or the name of a file in the backup folder, jars that aren't in it are removed from the mods folder")]
+        backup: PathBuf,
+        #[arg(long="mcdir", help="Use if you want to restore the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+        #[arg(long="minecraft-dir", conflicts_with="minecraft_dir", help="Path to a .minecraft folder to use instead of the detected one, its mods folder is used")]
+        minecraft_path: Option<PathBuf>,
+        #[arg(long="backup-dir", help="Where to look for the backup by name, overrides backup_dir of the config file")]
+        backup_dir: Option<PathBuf>,
+        #[arg(short, long, help="Replace the mods without asking for confirmation, removing jars that aren't in the backup needs it even with --quiet")]
+        yes: bool,
+    },
+    Validate {
+        #[arg(help="Path to the .mrpack file or its modrinth.index.json to check against Modrinth")]
+        file: PathBuf,
+    },
+    #[cfg(feature = "tui")]
+    Tui {
+        #[arg(short = 'v', long, help="The Minecraft version to browse and download mods for")]
+        game_version: String,
+        #[arg(short, long, help="The modloader for the mods")]
+        loader: String,
+        #[arg(long="mcdir", help="Use if you want to install the mods in the .minecraft\\mods folder")]
+        minecraft_dir: bool,
+    },
+    #[command(name="ratelimit")]
+    RateLimit,
+    #[command(name="selfupdate")]
+    SelfUpdate,
+    #[command(name="retry-failed")]
+    RetryFailed,
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    // Forgets the remembered slug to id mappings
+    Clear,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Default, PartialEq)]
+enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    // Only info, list and dependencies, the rest print text with it
+    Toml,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+enum SearchSort {
+    Relevance,
+    Downloads,
+    Follows,
+    Newest,
+    Updated,
+}
+
+impl SearchSort {
+    fn index(&self) -> &'static str {
+        match self {
+            SearchSort::Relevance => "relevance",
+            SearchSort::Downloads => "downloads",
+            SearchSort::Follows => "follows",
+            SearchSort::Newest => "newest",
+            SearchSort::Updated => "updated",
+        }
+    }
+
+    // Relevance isn't part of the hits, so it never sees two hits as equal
+    fn compare(&self, a: &Hit, b: &Hit) -> Ordering {
+        match self {
+            SearchSort::Relevance => Ordering::Equal,
+            SearchSort::Downloads => b.downloads.cmp(&a.downloads),
+            SearchSort::Follows => b.follows.cmp(&a.follows),
+            SearchSort::Newest => b.date_created.cmp(&a.date_created),
+            SearchSort::Updated => b.date_modified.cmp(&a.date_modified),
+        }
     }
 }
 
-#[derive(Deserialize)]
+#[derive(ValueEnum, Clone, Copy)]
+enum OrganizeBy {
+    Loader,
+    #[value(name="gameversion")]
+    GameVersion,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum DependencySort {
+    Name,
+    Type,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+enum VersionSelect {
+    Newest,
+    Widest,
+    Featured,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+enum Classifier {
+    Main,
+    Sources,
+    Javadoc,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum FileSort {
+    SizeAsc,
+    SizeDesc,
+    Name,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum GraphFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Default)]
+struct Settings {
+    format: OutputFormat,
+    verbose: bool,
+    pretty: bool,
+    quiet: bool,
+    explain: bool,
+    api_url: String,
+    user_agent: String,
+    token: Option<String>,
+    retries: u32,
+    retry_delay: u64,
+    show_ratelimit: bool,
+    ascii: bool,
+    color: bool,
+    jobs: u64,
+    progress: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct Config {
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+    backup_dir: Option<PathBuf>,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+#[derive(Debug)]
+enum RustdrynthError {
+    NotFound(String),
+    InvalidInput(String),
+    Conflict(String),
+    Network(String),
+    Parse(String),
+    Io(String),
+    Unauthorized(String),
+}
+
+impl RustdrynthError {
+    fn kind(&self) -> &'static str {
+        match self {
+            RustdrynthError::NotFound(_) => "not_found",
+            RustdrynthError::InvalidInput(_) => "invalid_input",
+            RustdrynthError::Conflict(_) => "conflict",
+            RustdrynthError::Network(_) => "network",
+            RustdrynthError::Parse(_) => "parse",
+            RustdrynthError::Io(_) => "io",
+            RustdrynthError::Unauthorized(_) => "unauthorized",
+        }
+    }
+}
+
+impl fmt::Display for RustdrynthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RustdrynthError::NotFound(message)
+            | RustdrynthError::InvalidInput(message)
+            | RustdrynthError::Conflict(message)
+            | RustdrynthError::Network(message)
+            | RustdrynthError::Parse(message)
+            | RustdrynthError::Io(message)
+            | RustdrynthError::Unauthorized(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for RustdrynthError {}
+
+// Errors bubbling up from reqwest, serde_json or std are sorted into a kind as well
+impl From<Box<dyn Error>> for RustdrynthError {
+    fn from(error: Box<dyn Error>) -> Self {
+        let error = match error.downcast::<RustdrynthError>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        let message = error.to_string();
+        if let Some(io_error) = error.downcast_ref::<io::Error>() {
+            match io_error.kind() {
+                io::ErrorKind::NotFound => RustdrynthError::NotFound(message),
+                io::ErrorKind::InvalidInput => RustdrynthError::InvalidInput(message),
+                _ => RustdrynthError::Io(message),
+            }
+        } else if error.is::<reqwest::Error>() {
+            RustdrynthError::Network(message)
+        } else if error.is::<serde_json::Error>() {
+            RustdrynthError::Parse(message)
+        } else {
+            RustdrynthError::Io(message)
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 struct ModrinthSearchResponse {
-    hits: Vec<Hit>
+    hits: Vec<Hit>,
+    total_hits: u64,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone)]
 struct ProjectResponse {
+    id: String,
+    #[serde(default)]
+    description: String,
     body: String,
     categories: Vec<String>,
+    #[serde(default)]
+    downloads: u64,
     title: String,
     project_type: String,
     slug: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    gallery: Vec<GalleryImage>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone)]
+struct GalleryImage {
+    url: String,
+    title: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
 struct Hit {
+    project_id: String,
+    project_type: String,
     slug: String,
     title: String,
     description: String,
+    downloads: u64,
+    follows: u64,
+    date_created: String,
+    date_modified: String,
 }
 
 #[derive(Deserialize)]
 struct ProjectVersion {
-     dependencies: Vec<ProjectDependency>
+    project_id: String,
+    name: String,
+    version_number: String,
+    dependencies: Vec<ProjectDependency>
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 struct ProjectDependency {
-    project_id: String,
+    // Missing for dependencies hosted outside of Modrinth, which only come with a file name
+    project_id: Option<String>,
+    version_id: Option<String>,
+    file_name: Option<String>,
     dependency_type: String,
 }
 
-#[derive(Deserialize)]
+impl ProjectDependency {
+    fn external_name(&self) -> String {
+        match (&self.file_name, &self.version_id) {
+            (Some(file_name), _) => file_name.clone(),
+            (None, Some(version_id)) => format!("version {}", version_id),
+            (None, None) => String::from("an unnamed file"),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 struct GameVersion {
+    project_id: String,
+    version_number: String,
+    featured: bool,
+    #[serde(default)]
+    game_versions: Vec<String>,
     loaders: Vec<String>,
     files: Vec<GameFiles>,
+    dependencies: Vec<ProjectDependency>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct VersionListing {
+    name: String,
+    version_number: String,
+    version_type: String,
+    loaders: Vec<String>,
+    game_versions: Vec<String>,
+    date_published: String,
+}
+
+#[derive(Serialize)]
+struct DependencyEntry {
+    dependency_type: String,
+    project_id: Option<String>,
+    slug: Option<String>,
+    title: Option<String>,
+    file_name: Option<String>,
+    status: &'static str,
+    error: Option<String>,
+}
+
+struct DependencyOptions {
+    sort_by: Option<DependencySort>,
+    no_sort: bool,
+    installed_dir: Option<String>,
+    include_external: bool,
+}
+
+struct SearchOptions {
+    count_only: bool,
+    compact: bool,
+    limit: usize,
+    max_results: Option<usize>,
+    description_length: Option<usize>,
+    sort: SearchSort,
+    secondary_sort: Option<SearchSort>,
+    installed_dir: Option<String>,
+    project_types: Vec<String>,
+    updated_since: Option<DateTime<Utc>>,
+    raw_facet: Option<RawFacet>,
+    dedicated_server: bool,
+}
+
+// Groups of facets as Modrinth takes them, the ones in a group are OR'ed together
+#[derive(Clone)]
+struct RawFacet(Vec<Vec<String>>);
+
+struct DownloadOptions {
+    loader: String,
+    game_version: String,
+    mcdir: bool,
+    minecraft_path: Option<PathBuf>,
+    with_deps: bool,
+    strict: bool,
+    missing_only: bool,
+    output_dir: Option<PathBuf>,
+    featured_only: bool,
+    select: VersionSelect,
+    filename: Option<String>,
+    filename_template: Option<String>,
+    extension: Option<String>,
+    sort_files: Option<FileSort>,
+    classifier: Classifier,
+    yes: bool,
+    dry_run: bool,
+    organize_by: Option<OrganizeBy>,
+    head_check: bool,
+    ignore_game_version: bool,
+    verify: bool,
+}
+
+impl DownloadOptions {
+    // Picks versions the way a plain `download` does, for the commands that only need to look them up
+    fn plain(loader: &str, game_version: &str) -> Self {
+        DownloadOptions {
+            loader: loader.to_string(),
+            game_version: game_version.to_string(),
+            mcdir: false,
+            minecraft_path: None,
+            with_deps: true,
+            strict: false,
+            missing_only: false,
+            output_dir: None,
+            featured_only: false,
+            select: VersionSelect::Newest,
+            filename: None,
+            filename_template: None,
+            extension: None,
+            sort_files: None,
+            classifier: Classifier::Main,
+            yes: false,
+            dry_run: false,
+            organize_by: None,
+            head_check: false,
+            ignore_game_version: false,
+            verify: true,
+        }
+    }
+
+    // Only plans made without any of the options that change which version or file is picked
+    // are cached, those are the ones `dependencies` and a plain `download --with-deps` share.
+    // The scope keeps plans from another API or token apart
+    fn plan_cache_key(&self, version: &GameVersion, scope: &str) -> Option<String> {
+        let plain = !self.featured_only && self.select == VersionSelect::Newest && self.extension.is_none() && self.sort_files.is_none() && self.classifier == Classifier::Main;
+        plain.then(|| format!("{} {}@{}:{}:{}", scope, version.project_id, version.version_number, self.loader, self.version_filter()))
+    }
+
+    // The game version the versions are looked up with, it's still used for --organize-by
+    fn version_filter(&self) -> &str {
+        if self.ignore_game_version { ANY_GAME_VERSION } else { &self.game_version }
+    }
+}
+
+#[derive(Deserialize)]
+struct GameVersionTag {
+    version: String,
+    version_type: String,
+}
+
+#[derive(Deserialize)]
+struct CategoryTag {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 struct GameFiles {
     url: String,
     filename: String,
+    size: u64,
+    #[serde(default)]
+    hashes: HashMap<String, String>,
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Serialize)]
+struct BatchResult {
+    project: String,
+    project_id: String,
+    action: &'static str,
+    version: String,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JarMetadata {
+    mod_id: String,
+    version: Option<String>,
+    loader: &'static str,
+    game_versions: Option<String>,
+}
+
+// A file of a batch that couldn't be downloaded, kept for retry-failed
+#[derive(Deserialize, Serialize)]
+struct FailedDownload {
+    project: String,
+    // Missing from files saved before it was kept
+    #[serde(default)]
+    project_id: String,
+    version: String,
+    target_dir: PathBuf,
+    files: Vec<GameFiles>,
+    verify: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CachedId {
+    id: String,
+    saved: i64,
+}
+
+// The required dependencies of a version, resolved recursively. The ones that couldn't be
+// resolved are kept next to the project that requires them, so a cached plan can still report them
+#[derive(Deserialize, Serialize, Clone)]
+struct DependencyPlan {
+    versions: Vec<GameVersion>,
+    missing: Vec<(String, String)>,
+    external: Vec<(String, String)>,
+    saved: i64,
+}
+
+#[derive(Deserialize)]
+struct Collection {
+    name: String,
+    projects: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ModpackIndex {
+    name: String,
+    files: Vec<ModpackFile>,
+    #[serde(default)]
+    dependencies: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct ModpackFile {
+    path: String,
+    hashes: ModpackHashes,
+    #[serde(rename="fileSize", default)]
+    file_size: u64,
+    env: Option<ModpackEnv>,
+    downloads: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ModpackHashes {
+    sha512: String,
+}
+
+#[derive(Deserialize)]
+struct ModpackEnv {
+    client: String,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // Loaded before parsing so the flags can fall back to them, dotenvy never
+    // overrides a variable that's already set so the real environment wins
+    let _ = dotenvy::dotenv();
+    if let Some(config_dir) = dirs::config_dir() {
+        let _ = dotenvy::from_path(config_dir.join("rustdrynth").join(".env"));
+    }
+    let cli = Cli::parse();
+    let _ = SETTINGS.set(Settings {
+        format: cli.format,
+        verbose: cli.verbose,
+        pretty: cli.pretty,
+        quiet: cli.quiet,
+        explain: cli.explain,
+        api_url: cli.api_url.clone(),
+        user_agent: cli.user_agent.clone(),
+        token: cli.token.clone(),
+        retries: cli.retries,
+        retry_delay: cli.retry_delay,
+        jobs: cli.jobs,
+        progress: !cli.no_progress && !cli.quiet,
+        show_ratelimit: cli.show_ratelimit,
+        ascii: cli.ascii || legacy_console(),
+        color: match cli.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        },
+    });
+    // Unlike the default one, a config file that was asked for has to be there
+    if let Some(path) = &cli.config {
+        if let Err(e) = load_config(path) {
+            report_error(RustdrynthError::from(e));
+            process::exit(1);
+        }
+    }
+    ctrlc::set_handler(handle_interrupt)?;
     if check_for_mods_dir().is_empty() {
-        println!("Couldn't find the .mincraft directory, mods won't be installed there even if asked for");
+        warn("Couldn't find the .mincraft directory, mods won't be installed there even if asked for");
     }
-    let client = Client::new();
+    let client = build_client(cli.proxy.as_deref())?;
 
-    let cli = Cli::parse();
-    let _ = match &cli.command {
-        Some(Commands::Search { query, categories, game_version }) => {
-            search_mods(query, game_version, categories.as_ref().unwrap().to_vec(), &client)
+    let result = match &cli.command {
+        Some(Commands::Search { query, categories, game_version, min_version, max_version, count_only, compact, limit, max_results, description_length, sort, secondary_sort, compatible_with_installed, minecraft_dir, minecraft_path, project_types, updated_since, raw_facet, dedicated_server }) => {
+            let game_versions = if min_version.is_some() || max_version.is_some() {
+                game_version_range(min_version.as_deref(), max_version.as_deref(), &client)
+            } else {
+                Ok(game_version.iter()
+                    .map(|game_version| normalize_game_version(game_version, !cli.no_version_check, &client))
+                    .filter(|game_version| game_version != ANY_GAME_VERSION)
+                    .collect())
+            };
+            let options = SearchOptions {
+                count_only: *count_only,
+                compact: *compact,
+                limit: *limit,
+                max_results: *max_results,
+                description_length: *description_length,
+                sort: *sort,
+                secondary_sort: *secondary_sort,
+                installed_dir: compatible_with_installed.then(|| mods_dir(*minecraft_dir, minecraft_path.as_deref())),
+                project_types: project_types.clone(),
+                updated_since: *updated_since,
+                raw_facet: raw_facet.clone(),
+                dedicated_server: *dedicated_server,
+            };
+            game_versions.and_then(|game_versions| {
+                let categories: Vec<String> = categories.iter().flatten().map(|category| expand_alias(category)).collect();
+                check_categories(&categories, &client);
+                search_mods(query, &game_versions, categories, &options, &client)
+            })
         },
 
-        Some(Commands::Download { project, game_version, loader, minecraft_dir }) => {
-            let game_files = get_download_link(project, loader, game_version, &client);
-            download_jar(game_files.unwrap(), &client, *minecraft_dir)
+        Some(Commands::Download { project, sha512, game_version, loader, minecraft_dir, minecraft_path, with_deps, no_deps, strict, missing_only, output_dir, featured_only, select, filename, extension, sort_files, classifier, filename_template, yes, dry_run, organize_by, head_check, ignore_game_version, no_verify }) => {
+            let options = DownloadOptions {
+                loader: loader.as_deref().map(expand_alias).unwrap_or_default(),
+                game_version: normalize_game_version(game_version, !cli.no_version_check, &client),
+                mcdir: *minecraft_dir,
+                minecraft_path: minecraft_path.clone(),
+                with_deps: *with_deps && !*no_deps,
+                strict: *strict,
+                missing_only: *missing_only,
+                output_dir: output_dir.clone(),
+                featured_only: *featured_only,
+                select: *select,
+                filename: filename.clone(),
+                filename_template: filename_template.clone(),
+                extension: extension.clone(),
+                sort_files: *sort_files,
+                classifier: *classifier,
+                yes: *yes,
+                dry_run: *dry_run,
+                organize_by: *organize_by,
+                head_check: *head_check,
+                ignore_game_version: *ignore_game_version,
+                verify: !*no_verify,
+            };
+            if options.ignore_game_version && options.game_version != ANY_GAME_VERSION {
+                warn(&format!("Warning: ignoring the game version, compatibility isn't guaranteed and the picked {} files may not work on {}", options.loader, options.game_version));
+            }
+            match sha512 {
+                Some(hash) => download_by_hash(hash, &options, &client),
+                None => download_project(project.as_deref().unwrap_or_default(), &options, &client),
+            }
         },
 
-        Some(Commands::Info { project }) => {
-            project_info(project, &client)
+        Some(Commands::Info { project, gallery, summary_only }) => {
+            project_info(project, *gallery, *summary_only, &client)
         },
 
-        Some(Commands::Dependencies { project, game_version, loader }) => {
-            let _ = project_dependencies(project, loader, game_version, &client);
-            Ok(())
-        }
+        Some(Commands::Versions { project, game_version, loader, filter }) => {
+            let game_version = normalize_game_version(game_version, !cli.no_version_check, &client);
+            list_versions(project, &loader.as_deref().map(expand_alias).unwrap_or_default(), &game_version, filter.as_deref(), &client)
+        },
+
+        Some(Commands::Dependencies { project, game_version, loader, sort_by, no_sort, export_graph, missing_only, minecraft_dir, minecraft_path, include_external_deps }) => {
+            let game_version = normalize_game_version(game_version, !cli.no_version_check, &client);
+            match export_graph {
+                Some(graph_format) => export_dependency_graph(project, &expand_alias(loader), &game_version, *graph_format, &client),
+                None => {
+                    let options = DependencyOptions {
+                        sort_by: *sort_by,
+                        no_sort: *no_sort,
+                        installed_dir: missing_only.then(|| mods_dir(*minecraft_dir, minecraft_path.as_deref())),
+                        include_external: *include_external_deps,
+                    };
+                    project_dependencies(project, &expand_alias(loader), &game_version, &options, &client)
+                },
+            }
+        },
+
+        Some(Commands::Add { query, game_version, loader, minecraft_dir, minecraft_path, yes, no_deps, strict, featured_only, select, dry_run, extension, sort_files }) => {
+            let options = DownloadOptions {
+                loader: expand_alias(loader),
+                game_version: normalize_game_version(game_version, !cli.no_version_check, &client),
+                mcdir: *minecraft_dir,
+                minecraft_path: minecraft_path.clone(),
+                with_deps: !*no_deps,
+                strict: *strict,
+                missing_only: false,
+                output_dir: None,
+                featured_only: *featured_only,
+                select: *select,
+                filename: None,
+                filename_template: None,
+                extension: extension.clone(),
+                sort_files: *sort_files,
+                classifier: Classifier::Main,
+                yes: *yes,
+                dry_run: *dry_run,
+                organize_by: None,
+                head_check: false,
+                ignore_game_version: false,
+                verify: true,
+            };
+            add_mod(query, &options, &client)
+        },
+
+        Some(Commands::Check { projects, file, game_version, loader }) => {
+            let game_version = normalize_game_version(game_version, !cli.no_version_check, &client);
+            read_mod_list(projects, file.as_deref())
+                .and_then(|projects| check_mod_set(&projects, &expand_alias(loader), &game_version, &client))
+        },
+
+        Some(Commands::GameVersions { allow_snapshots }) => {
+            list_game_versions(*allow_snapshots, &client)
+        },
+
+        Some(Commands::Remove { project, minecraft_dir, minecraft_path, force }) => {
+            remove_mod(project, &mods_dir(*minecraft_dir, minecraft_path.as_deref()), *force, &client)
+        },
+
+        Some(Commands::Update { game_version, loader, minecraft_dir, minecraft_path, keep_old, yes }) => {
+            let game_version = normalize_game_version(game_version, !cli.no_version_check, &client);
+            update_mods(&mods_dir(*minecraft_dir, minecraft_path.as_deref()), &expand_alias(loader), &game_version, *keep_old, *yes, &client)
+        },
+
+        Some(Commands::Prune { game_version, loader, minecraft_dir, minecraft_path, dry_run, keep_old }) => {
+            let game_version = normalize_game_version(game_version, !cli.no_version_check, &client);
+            prune_mods(&mods_dir(*minecraft_dir, minecraft_path.as_deref()), &expand_alias(loader), &game_version, *dry_run, *keep_old, &client)
+        },
+
+        Some(Commands::Watch { game_version, loader, minecraft_dir, minecraft_path, interval, auto_update, once }) => {
+            let game_version = normalize_game_version(game_version, !cli.no_version_check, &client);
+            watch_mods(&mods_dir(*minecraft_dir, minecraft_path.as_deref()), &expand_alias(loader), &game_version, *interval, *auto_update, *once, &client)
+        },
+
+        Some(Commands::Backup { minecraft_dir, minecraft_path, backup_dir }) => {
+            resolve_backup_dir(backup_dir.as_deref())
+                .and_then(|backup_dir| backup_mods(&mods_dir(*minecraft_dir, minecraft_path.as_deref()), &backup_dir))
+        },
+
+        Some(Commands::Restore { backup, minecraft_dir, minecraft_path, backup_dir, yes }) => {
+            let backup = if backup.exists() { Ok(backup.clone()) } else { resolve_backup_dir(backup_dir.as_deref()).map(|backup_dir| backup_dir.join(backup)) };
+            backup.and_then(|backup| restore_mods(&backup, &mods_dir(*minecraft_dir, minecraft_path.as_deref()), *yes))
+        },
+
+        Some(Commands::List { minecraft_dir, minecraft_path }) => {
+            list_mods(&mods_dir(*minecraft_dir, minecraft_path.as_deref()), &client)
+        },
+
+        Some(Commands::Verify { minecraft_dir, minecraft_path }) => {
+            verify_mods(&mods_dir(*minecraft_dir, minecraft_path.as_deref()), &client)
+        },
+
+        Some(Commands::WhatIs { file }) => {
+            identify_file(file, &client)
+        },
+
+        Some(Commands::Collection { collection, game_version, loader, minecraft_dir, minecraft_path, no_deps, exclude, yes, dry_run }) => {
+            let options = DownloadOptions {
+                loader: expand_alias(loader),
+                game_version: normalize_game_version(game_version, !cli.no_version_check, &client),
+                mcdir: *minecraft_dir,
+                minecraft_path: minecraft_path.clone(),
+                with_deps: !*no_deps,
+                strict: false,
+                missing_only: false,
+                output_dir: None,
+                featured_only: false,
+                select: VersionSelect::Newest,
+                filename: None,
+                filename_template: None,
+                extension: None,
+                sort_files: None,
+                classifier: Classifier::Main,
+                yes: *yes,
+                dry_run: *dry_run,
+                organize_by: None,
+                head_check: false,
+                ignore_game_version: false,
+                verify: true,
+            };
+            install_collection(collection, exclude, &options, &client)
+        },
+
+        Some(Commands::Modpack { file, output_dir, exclude, yes }) => {
+            install_modpack(file, output_dir.as_deref().unwrap_or(Path::new(".")), exclude, *yes, &client)
+        },
+
+        Some(Commands::Diff { old, new }) => {
+            diff_mod_sets(old, new, &client)
+        },
+
+        Some(Commands::Validate { file }) => {
+            validate_modpack(file, &client)
+        },
+
+        #[cfg(feature = "tui")]
+        Some(Commands::Tui { game_version, loader, minecraft_dir }) => {
+            let options = DownloadOptions {
+                loader: expand_alias(loader),
+                game_version: normalize_game_version(game_version, !cli.no_version_check, &client),
+                mcdir: *minecraft_dir,
+                minecraft_path: None,
+                with_deps: true,
+                strict: false,
+                missing_only: false,
+                output_dir: None,
+                featured_only: false,
+                select: VersionSelect::Newest,
+                filename: None,
+                filename_template: None,
+                extension: None,
+                sort_files: None,
+                classifier: Classifier::Main,
+                yes: false,
+                dry_run: false,
+                organize_by: None,
+                head_check: false,
+                ignore_game_version: false,
+                verify: true,
+            };
+            browse(&options, &client)
+        },
+
+        Some(Commands::RateLimit) => {
+            show_ratelimit(&client)
+        },
+
+        Some(Commands::SelfUpdate) => {
+            check_for_update(&client)
+        },
+
+        Some(Commands::RetryFailed) => {
+            retry_failed(&client)
+        },
+
+        Some(Commands::Cache { action: CacheAction::Clear }) => {
+            clear_cache()
+        },
 
         _ => {
             println!("no command found");
             Ok(())
         }
     };
+    if let Err(e) = result {
+        report_error(RustdrynthError::from(e));
+        process::exit(1);
+    }
     Ok(())
 }
 
-fn adapt_to_facet(categories: Vec<String>, game_version: &str) -> String {
-    let mut facet = "&facets=[[\"project_type=mod\"],".to_string();
-    let facet_1 = "[\"";
-    let facet_2 = "\"],";
-    for element in categories.iter() {
-        let filter = format!("{}categories:{}{}", facet_1, element, facet_2);
-        facet.push_str(&filter);
+fn report_error(error: RustdrynthError) {
+    match settings().format {
+        OutputFormat::Json => eprintln!("{}", serde_json::json!({ "error": error.to_string(), "kind": error.kind() })),
+        OutputFormat::Text | OutputFormat::Toml => eprintln!("{}", error),
     }
-    if !game_version.is_empty() {
-        let version = format!("{}versions:{}{}", facet_1, game_version, facet_2);
-        facet.push_str(&version);
+}
+
+// Shows the exact url of every request made, facets and params included
+fn explain(method: &str, url: &str) {
+    if settings().explain {
+        eprintln!("{} {}", method, url);
+    }
+}
+
+fn status(message: &str) {
+    if !settings().quiet {
+        progress().suspend(|| println!("{}", message));
+    }
+}
+
+fn verbose(message: &str) {
+    if settings().verbose {
+        progress().suspend(|| eprintln!("{}", message));
     }
-    facet.push_str(facet_2);
-    facet = remove_last_char(&facet, ',');
-    facet = remove_last_char(&facet, '"');
-    remove_last_char(&facet, ',')
 }
 
-fn search_mods(query: &str, game_version: &str, categories: Vec<String>, client: &Client) -> Result<(), Box<dyn Error>> {
-    let facet = {
-        let vec_buff: Vec<String> = categories.to_vec();
-        if !vec_buff.is_empty() {
-            adapt_to_facet(vec_buff, game_version)
+// Holds the bar of every download in flight, messages printed meanwhile are suspended
+// around it so they don't tear the bars. Hidden with --no-progress, --quiet or when
+// stdout isn't a terminal, e.g. in CI logs
+fn progress() -> &'static MultiProgress {
+    static PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+    PROGRESS.get_or_init(|| {
+        if settings().progress && io::stdout().is_terminal() {
+            MultiProgress::new()
         } else {
-            String::new()
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
         }
+    })
+}
 
-    };
-    let search_link = format!("https://api.modrinth.com/v2/search?query={}{}", query.trim(), facet);
-    let resp = client.get(search_link).header(USER_AGENT, "https://github.com/Tomyatana/Pydrinth/tree/Rustdrynth").send()?;
-    let resp_txt = resp.text()?;
-    let processed_response: Result<ModrinthSearchResponse, _> = serde_json::from_str(&resp_txt);
+fn download_bar(game_file: &GameFiles) -> ProgressBar {
+    let bar = progress().add(ProgressBar::new(game_file.size));
+    bar.set_style(ProgressStyle::with_template("{msg} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec}").unwrap_or_else(|_| ProgressStyle::default_bar()));
+    bar.set_message(game_file.filename.clone());
+    bar
+}
 
-    let hits = match processed_response {
-        Ok(modrinth_response) => modrinth_response.hits,
-        Err(e) =>{ 
-            println!("Couldn't find any mods matching the query");
-            return Err(Box::new(e))
-        }
-    };
+// How a project is named in the text output, so either the slug or the id can be copied
+fn project_handle(slug: &str, id: &str) -> String {
+    format!("{} [{}]", slug, id)
+}
 
-    for hit in hits.iter() {
-        println!("\"{}\" : {}", hit.title, hit.slug);
-        println!("{}\n", hit.description);
+fn terminal_text(text: &str) -> String {
+    if settings().ascii {
+        deunicode::deunicode(text)
+    } else {
+        text.to_string()
     }
-    Err(Box::new(std::io::Error::new(std::io::ErrorKind::NotFound, "No matching GameFiles found")))
 }
 
-fn project_dependencies(project: &str, loader: &str, game_version: &str, client: &Client) -> Result<(), Box<dyn Error>> {
-    let resp = client.get(format!("https://api.modrinth.com/v2/project/{}/version?loader=[\"{}\"]&game_versions=[\"{}\"]", project, loader, game_version))
-        .header(USER_AGENT, "https://github.com/Tomyatana/Pydrinth/tree/Rustdrynth").send()?;
-    let resp_txt = resp.text()?;
-    let processed_resp: Result<Vec<ProjectVersion>, serde_json::Error> = serde_json::from_str(&resp_txt);
+// Legacy Windows code pages garble most of unicode, 65001 is UTF-8 and 0 means the output
+// isn't a console at all
+#[cfg(windows)]
+fn legacy_console() -> bool {
+    extern "system" {
+        fn GetConsoleOutputCP() -> u32;
+    }
+    !matches!(unsafe { GetConsoleOutputCP() }, 0 | 65001)
+}
+
+#[cfg(not(windows))]
+fn legacy_console() -> bool {
+    false
+}
+
+// The API's project types read like "resourcepack", the JSON and TOML output keep them that way
+fn project_type_label(project_type: &str) -> String {
+    let (icon, label) = match project_type {
+        "mod" => ("🧩", "Mod"),
+        "modpack" => ("📦", "Modpack"),
+        "resourcepack" => ("🎨", "Resource Pack"),
+        "shader" => ("✨", "Shader"),
+        "datapack" => ("📜", "Data Pack"),
+        "plugin" => ("🔌", "Plugin"),
+        _ => return project_type.to_string(),
+    };
+    // --ascii would only turn the icon into a word
+    if settings().color && !settings().ascii {
+        format!("{} {}", icon, label)
+    } else {
+        label.to_string()
+    }
+}
+
+fn structured_output() -> bool {
+    settings().format != OutputFormat::Text
+}
+
+// For the commands that also support TOML, `key` names the array when the value isn't a table
+fn print_structured<T: Serialize>(key: &str, value: &T) -> Result<(), Box<dyn Error>> {
+    match settings().format {
+        OutputFormat::Toml => print_toml(key, value),
+        _ => print_json(value),
+    }
+}
+
+// TOML has no null and its top level has to be a table, so the value goes through JSON to
+// drop the nulls and is nested under `key` if needed
+fn print_toml<T: Serialize>(key: &str, value: &T) -> Result<(), Box<dyn Error>> {
+    let mut value = serde_json::to_value(value)?;
+    strip_nulls(&mut value);
+    if !value.is_object() {
+        value = serde_json::json!({ key: value });
+    }
+    print!("{}", toml::to_string(&value)?);
+    Ok(())
+}
+
+fn strip_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(items) => {
+            items.retain(|value| !value.is_null());
+            items.iter_mut().for_each(strip_nulls);
+        }
+        _ => {}
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), Box<dyn Error>> {
+    if settings().pretty {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        println!("{}", serde_json::to_string(value)?);
+    }
+    Ok(())
+}
+
+// Notices go to stderr in json mode so stdout stays parseable
+fn warn(message: &str) {
+    progress().suspend(|| match settings().format {
+        OutputFormat::Json | OutputFormat::Toml => eprintln!("{}", message),
+        OutputFormat::Text => println!("{}", message),
+    })
+}
+
+fn build_client(proxy: Option<&str>) -> Result<Client, Box<dyn Error>> {
+    // Without an explicit proxy reqwest already picks up HTTP_PROXY, HTTPS_PROXY and NO_PROXY.
+    // Compressed responses are asked for and decoded transparently, search pages shrink a lot
+    let mut builder = Client::builder().gzip(true).brotli(true).deflate(true).redirect(Policy::limited(MAX_REDIRECTS));
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(Proxy::all(proxy)?.no_proxy(NoProxy::from_env()));
+    }
+    Ok(builder.build()?)
+}
+
+// Every inner list of the facet is OR'ed together, so all the game versions go in the same list
+fn adapt_to_facet(project_types: &[String], categories: Vec<String>, game_versions: &[String]) -> String {
+    let types: Vec<String> = project_types.iter().map(|project_type| format!("\"project_type={}\"", project_type)).collect();
+    let mut facet = format!("[[{}],", types.join(","));
+    let facet_1 = "[\"";
+    let facet_2 = "\"],";
+    for element in categories.iter() {
+        let filter = format!("{}categories:{}{}", facet_1, element, facet_2);
+        facet.push_str(&filter);
+    }
+    if !game_versions.is_empty() {
+        let versions: Vec<String> = game_versions.iter().map(|game_version| format!("\"versions:{}\"", game_version)).collect();
+        facet.push_str(&format!("[{}],", versions.join(",")));
+    }
+    facet.push_str(facet_2);
+    facet = remove_last_char(&facet, ',');
+    facet = remove_last_char(&facet, '"');
+    remove_last_char(&facet, ',')
+}
+
+fn search_mods(query: &str, game_versions: &[String], categories: Vec<String>, options: &SearchOptions, client: &Client) -> Result<(), Box<dyn Error>> {
+    check_secondary_sort(options.sort, options.secondary_sort)?;
+    let mut facet = with_raw_facet(adapt_to_facet(&options.project_types, categories, game_versions), options.raw_facet.as_ref());
+    if options.dedicated_server {
+        // Optional on the client still fits, a mod the client has to install too doesn't
+        let sides = RawFacet(vec![vec!["server_side:required".to_string()], vec!["client_side!=required".to_string()]]);
+        facet = with_raw_facet(facet, Some(&sides));
+    }
+    if options.count_only {
+        let total_hits = fetch_hits(query, &facet, options.sort, 1, client)?.total_hits;
+        match settings().format {
+            OutputFormat::Json => print_json(&serde_json::json!({ "total_hits": total_hits }))?,
+            OutputFormat::Text | OutputFormat::Toml => println!("{}", total_hits),
+        }
+        return Ok(());
+    }
+    // Every page is fetched with the same index and offsets, so the order holds across pages
+    let limit = if let Some(max_results) = options.max_results {
+        max_results
+    } else if options.limit > MAX_SEARCH_LIMIT {
+        warn(&format!("Only the first {} mods can be listed, the results will be truncated", MAX_SEARCH_LIMIT));
+        MAX_SEARCH_LIMIT
+    } else {
+        options.limit
+    };
+    let mut search_response = fetch_hits(query, &facet, options.sort, limit, client)?;
+    if let Some(secondary_sort) = options.secondary_sort {
+        sort_hits(&mut search_response.hits, options.sort, secondary_sort);
+    }
+    // The filters below run on the fetched page, so the counts are taken before them
+    let fetched = search_response.hits.len();
+    if let Some(installed_dir) = &options.installed_dir {
+        let installed = installed_mods(installed_dir, client)?;
+        let mut compatible = Vec::new();
+        for hit in search_response.hits {
+            match incompatibility(&hit, &installed, client)? {
+                Some(reason) => verbose(&format!("Left out {}, {}", hit.slug, reason)),
+                None => compatible.push(hit),
+            }
+        }
+        search_response.hits = compatible;
+    }
+    if let Some(updated_since) = options.updated_since {
+        search_response.hits.retain(|hit| DateTime::parse_from_rfc3339(&hit.date_modified).is_ok_and(|date_modified| date_modified > updated_since));
+    }
+
+    if settings().format == OutputFormat::Json {
+        print_json(&search_response)?;
+        return Ok(());
+    }
+    let ModrinthSearchResponse { hits, total_hits } = search_response;
+    // With a single type every result has it, so it's only worth printing when mixing them
+    let label = |hit: &Hit| if options.project_types.len() > 1 { format!("[{}] ", project_type_label(&hit.project_type)) } else { String::new() };
+    for hit in hits.iter() {
+        if options.compact {
+            let length = options.description_length.unwrap_or(COMPACT_DESCRIPTION_LENGTH);
+            println!("{}", terminal_text(&format!("{}{} ({}) — {} downloads — {}", label(hit), hit.title, project_handle(&hit.slug, &hit.project_id), hit.downloads, truncate(&hit.description, length))));
+        } else {
+            let description = match options.description_length {
+                Some(length) => truncate(&hit.description, length),
+                None => hit.description.clone(),
+            };
+            println!("{}", terminal_text(&format!("{}\"{}\" : {}", label(hit), hit.title, project_handle(&hit.slug, &hit.project_id))));
+            println!("{}\n", terminal_text(&description));
+        }
+    }
+    if (fetched as u64) < total_hits {
+        // --limit can't go past its cap, so from there on only --max-results lists more
+        let flag = if options.max_results.is_some() || limit >= MAX_SEARCH_LIMIT { "--max-results" } else { "--limit" };
+        println!("Showing the first {} of {} mods, use {} to list more", fetched, total_hits, flag);
+    }
+    if hits.len() < fetched {
+        println!("Left out {} of the {} mods fetched, they didn't pass the filters", fetched - hits.len(), fetched);
+    }
+    Ok(())
+}
+
+// A single group like '["categories:fabric"]' is also taken, as if it were wrapped in another array
+fn parse_raw_facet(raw_facet: &str) -> Result<RawFacet, String> {
+    let value: serde_json::Value = serde_json::from_str(raw_facet).map_err(|e| format!("isn't valid JSON: {}", e))?;
+    if let Ok(groups) = serde_json::from_value::<Vec<Vec<String>>>(value.clone()) {
+        return Ok(RawFacet(groups));
+    }
+    match serde_json::from_value::<Vec<String>>(value) {
+        Ok(group) => Ok(RawFacet(vec![group])),
+        Err(_) => Err("must be an array of arrays of facet strings, e.g. '[[\"categories:fabric\"]]'".to_string()),
+    }
+}
+
+// Relevance only exists as the API's order, without a score to tell which hits tie, so
+// there's nothing a secondary sort could break
+fn check_secondary_sort(sort: SearchSort, secondary_sort: Option<SearchSort>) -> Result<(), Box<dyn Error>> {
+    if secondary_sort.is_some() && matches!(sort, SearchSort::Relevance) {
+        return Err(Box::new(RustdrynthError::InvalidInput("--secondary-sort needs a --sort other than relevance, e.g. --sort downloads".to_string())));
+    }
+    Ok(())
+}
+
+fn sort_hits(hits: &mut [Hit], sort: SearchSort, secondary_sort: SearchSort) {
+    // Stable, so hits that tie on both keys stay in the order Modrinth returned them
+    hits.sort_by(|a, b| sort.compare(a, b).then_with(|| secondary_sort.compare(a, b)));
+}
+
+// Takes a date like "2024-05-01", a full RFC 3339 timestamp, or a number of days or weeks
+// back from now like "30d" or "2w"
+fn parse_since(since: &str) -> Result<DateTime<Utc>, String> {
+    let since = since.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc());
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(since) {
+        return Ok(date.with_timezone(&Utc));
+    }
+    let days_per_unit = match since.chars().last() {
+        Some('d') => 1,
+        Some('w') => 7,
+        _ => return Err(format!("\"{}\" isn't a date like 2024-05-01 or a duration like 30d", since)),
+    };
+    match since[..since.len() - 1].parse::<i64>() {
+        Ok(amount) if amount >= 0 => Ok(Utc::now() - TimeDelta::days(amount * days_per_unit)),
+        _ => Err(format!("\"{}\" isn't a date like 2024-05-01 or a duration like 30d", since)),
+    }
+}
+
+// Checks the newest version of the hit against the installed mods and the installed
+// mods against the hit, returns why they can't be used together if they can't
+fn incompatibility(hit: &Hit, installed: &[(PathBuf, ProjectVersion)], client: &Client) -> Result<Option<String>, Box<dyn Error>> {
+    for (path, version) in installed {
+        if version.dependencies.iter().any(|dependency| dependency.dependency_type == "incompatible" && dependency.project_id.as_ref() == Some(&hit.project_id)) {
+            return Ok(Some(format!("the installed {} is incompatible with it", path.display())));
+        }
+    }
+    let resp_txt = api_get(client, &format!("/project/{}/version", hit.project_id), &[])?;
+    let versions: Vec<ProjectVersion> = serde_json::from_str(&resp_txt)?;
+    let Some(newest) = versions.first() else {
+        return Ok(None);
+    };
+    for dependency in newest.dependencies.iter().filter(|dependency| dependency.dependency_type == "incompatible") {
+        if let Some((path, _)) = installed.iter().find(|(_, version)| dependency.project_id.as_ref() == Some(&version.project_id)) {
+            return Ok(Some(format!("it's incompatible with the installed {}", path.display())));
+        }
+    }
+    Ok(None)
+}
+
+// The raw groups go in as they were given, after the closing bracket of the last generated one
+fn with_raw_facet(facet: String, raw_facet: Option<&RawFacet>) -> String {
+    match raw_facet {
+        Some(RawFacet(groups)) if !groups.is_empty() => {
+            let groups: Vec<String> = groups.iter().map(|group| serde_json::json!(group).to_string()).collect();
+            format!("{},{}]", remove_last_char(&facet, ']'), groups.join(","))
+        },
+        _ => facet,
+    }
+}
+
+// Pages through the search results until `limit` hits are gathered or there are no more
+fn fetch_hits(query: &str, facet: &str, sort: SearchSort, limit: usize, client: &Client) -> Result<ModrinthSearchResponse, Box<dyn Error>> {
+    let mut hits: Vec<Hit> = Vec::new();
+    let mut total_hits = 0;
+    while hits.len() < limit {
+        let page_size = SEARCH_PAGE_SIZE.min(limit - hits.len());
+        let resp_txt = api_get(client, "/search", &[
+            ("query", query.trim()),
+            ("index", sort.index()),
+            ("facets", facet),
+            ("limit", &page_size.to_string()),
+            ("offset", &hits.len().to_string()),
+        ])?;
+        let processed_response: Result<ModrinthSearchResponse, _> = serde_json::from_str(&resp_txt);
+
+        let modrinth_response = match processed_response {
+            Ok(modrinth_response) => modrinth_response,
+            Err(e) =>{ 
+                return Err(Box::new(RustdrynthError::Parse(format!("Couldn't find any mods matching the query: {}", e))))
+            }
+        };
+        total_hits = modrinth_response.total_hits;
+        let page_len = modrinth_response.hits.len();
+        hits.extend(modrinth_response.hits);
+        if page_len < page_size {
+            break;
+        }
+    }
+    Ok(ModrinthSearchResponse { hits, total_hits })
+}
+
+// Newest first, as the API returns them. The filter is matched case-insensitively against
+// the version's name and number after fetching
+fn list_versions(project: &str, loader: &str, game_version: &str, filter: Option<&str>, client: &Client) -> Result<(), Box<dyn Error>> {
+    let resp_txt = get_version_list(project, loader, game_version, client)?;
+    let mut versions: Vec<VersionListing> = match serde_json::from_str(&resp_txt) {
+        Ok(versions) => versions,
+        Err(e) => return Err(Box::new(RustdrynthError::Parse(format!("Couldn't get the versions of \"{}\": {}{}", project, e, token_hint())))),
+    };
+    let total = versions.len();
+    if let Some(filter) = filter {
+        let filter = filter.to_lowercase();
+        versions.retain(|version| version.name.to_lowercase().contains(&filter) || version.version_number.to_lowercase().contains(&filter));
+    }
+    if settings().format == OutputFormat::Json {
+        return print_json(&versions);
+    }
+    for version in versions.iter() {
+        println!("{}", terminal_text(&format!("{} - {} [{}] ({}) for {}", version.version_number, version.name, version.version_type, version.loaders.join(", "), version.game_versions.join(", "))));
+    }
+    match filter {
+        Some(filter) => println!("{} of {} versions match \"{}\"", versions.len(), total, filter),
+        None if versions.is_empty() => println!("No versions found"),
+        None => {}
+    }
+    Ok(())
+}
+
+// With an installed_dir only the required dependencies missing from it are listed
+fn project_dependencies(project: &str, loader: &str, game_version: &str, options: &DependencyOptions, client: &Client) -> Result<(), Box<dyn Error>> {
+    let resp_txt = get_version_list(project, loader, game_version, client)?;
+    let processed_resp: Result<Vec<ProjectVersion>, serde_json::Error> = serde_json::from_str(&resp_txt);
     match processed_resp {
         Ok(prj_versions) => {
-            let first_prj_v = prj_versions.first().unwrap();
+            let first_prj_v = match prj_versions.first() {
+                Some(first_prj_v) => first_prj_v,
+                None => return Err(Box::new(RustdrynthError::NotFound(format!("Couldn't find a {} {} version of {}", loader, game_version, project)))),
+            };
             if !first_prj_v.dependencies.is_empty(){
-                for dependency in first_prj_v.dependencies.iter() {
-                    let dependency_project = get_project(&dependency.project_id, client).unwrap();
-                    println!("{}: \"{}\" - {}", dependency.dependency_type, dependency_project.title, dependency_project.slug)
+                let (mut external, hosted): (Vec<ProjectDependency>, Vec<ProjectDependency>) = first_prj_v.dependencies.iter()
+                    .cloned().partition(|dependency| dependency.project_id.is_none());
+                let project_ids: Vec<String> = hosted.iter().filter_map(|dependency| dependency.project_id.clone()).collect();
+                let projects = get_projects(&project_ids, client)?;
+                let mut resolved: Vec<(ProjectDependency, ProjectResponse)> = Vec::new();
+                let mut failed: Vec<(ProjectDependency, String, String)> = Vec::new();
+                for (dependency, project_id) in hosted.into_iter().zip(project_ids.iter()) {
+                    match projects.get(project_id).cloned().map_or_else(|| get_project(project_id, client), Ok) {
+                        Ok(dependency_project) => resolved.push((dependency, dependency_project)),
+                        Err(e) => failed.push((dependency, project_id.clone(), e.to_string())),
+                    }
+                };
+                if let Some(installed_dir) = &options.installed_dir {
+                    // External files can't be matched against the installed mods, so the required ones are always listed
+                    let installed = installed_project_ids(installed_dir, client)?;
+                    resolved.retain(|(dependency, prj)| dependency.dependency_type == "required" && !installed.contains(&prj.id));
+                    external.retain(|dependency| dependency.dependency_type == "required");
+                    failed.retain(|(dependency, project_id, _)| dependency.dependency_type == "required" && !installed.contains(project_id));
+                    if resolved.is_empty() && failed.is_empty() && (external.is_empty() || !options.include_external) {
+                        if structured_output() {
+                            return print_structured("dependencies", &Vec::<DependencyEntry>::new());
+                        }
+                        println!("Every required dependency is already installed in {}", installed_dir);
+                        return Ok(());
+                    }
+                }
+                // A required dependency without a build for the same loader and version can't actually run alongside.
+                // It's resolved like `download --with-deps` would, which reuses the plan afterwards
+                let plan_options = DownloadOptions::plain(loader, game_version);
+                let unavailable: HashSet<String> = match get_version(project, &plan_options, client) {
+                    Ok(version) => dependency_plan(&version, &plan_options, client)?.missing.into_iter().map(|(_, dependency_id)| dependency_id).collect(),
+                    Err(e) if is_not_found(&*e) => HashSet::new(),
+                    Err(e) => return Err(e),
                 };
+                if structured_output() {
+                    sort_dependencies(&mut resolved, options);
+                    return print_structured("dependencies", &dependency_entries(&resolved, &unavailable, &failed, &external, options.include_external));
+                }
+                for (_, project_id, error) in failed.iter() {
+                    warn(&format!("Warning: couldn't get the dependency {}: {}", project_id, error));
+                }
+                match options.sort_by {
+                    Some(DependencySort::Name) => {
+                        sort_dependencies(&mut resolved, options);
+                        print_dependencies(&resolved);
+                    },
+                    Some(DependencySort::Type) => {
+                        for dependency_type in DEPENDENCY_TYPE_ORDER {
+                            let group: Vec<(ProjectDependency, ProjectResponse)> = resolved.iter()
+                                .filter(|(dependency, _)| dependency.dependency_type == dependency_type)
+                                .cloned().collect();
+                            if !group.is_empty() {
+                                println!("{}:", dependency_type);
+                                print_dependencies(&group);
+                                println!();
+                            }
+                        }
+                        // Types the API may add later still get listed
+                        let other: Vec<(ProjectDependency, ProjectResponse)> = resolved.iter()
+                            .filter(|(dependency, _)| !DEPENDENCY_TYPE_ORDER.contains(&dependency.dependency_type.as_str()))
+                            .cloned().collect();
+                        if !other.is_empty() {
+                            println!("other:");
+                            print_dependencies(&other);
+                            println!();
+                        }
+                    },
+                    None => {
+                        sort_dependencies(&mut resolved, options);
+                        print_dependencies(&resolved);
+                    },
+                }
+                if !external.is_empty() {
+                    if options.include_external {
+                        println!("External dependencies, these aren't on Modrinth and have to be obtained manually:");
+                        for dependency in external.iter() {
+                            println!("{}: {}", dependency.dependency_type, terminal_text(&dependency.external_name()));
+                        }
+                    } else {
+                        status(&format!("{} dependencies aren't hosted on Modrinth, use --include-external-deps to list them", external.len()));
+                    }
+                }
+                for (_, dependency_project) in resolved.iter().filter(|(_, prj)| unavailable.contains(&prj.id)) {
+                    warn(&format!("Warning: the required dependency {} has no {} {} version", dependency_project.title, loader, game_version));
+                }
+            } else if structured_output() {
+                print_structured("dependencies", &Vec::<DependencyEntry>::new())?;
             } else {
                 println!("No dependencies found on this project's version");
-                return Err(Box::new(io::Error::new(io::ErrorKind::NotFound, "No dependencies found for this project's version")));
             }
         },
         Err(e) => {
-            println!("{}", e);
             return Err(Box::new(e))
         }
     };
     Ok(())
 }
 
-fn project_info(project: &str, client: &Client) -> Result<(), Box<dyn Error>> {
-    let resp = client.get(format!("https://api.modrinth.com/v2/project/{}", project))
-        .header(USER_AGENT, "https://github.com/Tomyatana/Pydrinth/tree/Rustdrynth").send()?;
-    let processed_resp: Result<ProjectResponse, serde_json::Error> = serde_json::from_str(&resp.text()?);
+const DEFAULT_API_URL: &str = "https://api.modrinth.com/v2";
+const DEFAULT_USER_AGENT: &str = "https://github.com/Tomyatana/Pydrinth/tree/Rustdrynth";
+// Counts every request of a redirect chain, the first one included
+const MAX_REDIRECTS: usize = 5;
+// Leaves the game version out of every filter, for version independent content
+const ANY_GAME_VERSION: &str = "any";
+const DEFAULT_WATCH_INTERVAL: u64 = 60;
+// 30 days, in seconds
+const SLUG_CACHE_TTL: i64 = 30 * 24 * 60 * 60;
+const DEPENDENCY_PLAN_TTL: i64 = 15 * 60;
+const DEFAULT_RETRIES: u32 = 2;
+const DEFAULT_RETRY_DELAY: u64 = 1;
+// Kept low so a big batch doesn't hammer Modrinth
+const DEFAULT_JOBS: u64 = 4;
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+const MAX_SEARCH_LIMIT: usize = 100;
+// The most the API returns in one request, bigger limits are fetched across several pages
+const SEARCH_PAGE_SIZE: usize = 100;
+// Only a screenful is listed in the tui
+#[cfg(feature = "tui")]
+const TUI_SEARCH_LIMIT: usize = 20;
+
+const COMPACT_DESCRIPTION_LENGTH: usize = 60;
+
+const DEPENDENCY_TYPE_ORDER: [&str; 4] = ["required", "optional", "incompatible", "embedded"];
+
+// Walks every dependency except the incompatible ones, which are only kept as edges. A
+// project shared by several mods is a single node
+fn export_dependency_graph(project: &str, loader: &str, game_version: &str, graph_format: GraphFormat, client: &Client) -> Result<(), Box<dyn Error>> {
+    let root = resolve_project_id(project, client)?;
+    let mut nodes = vec![root.clone()];
+    let mut edges: Vec<(String, String, String)> = Vec::new();
+    let mut pending = vec![root];
+    while let Some(project_id) = pending.pop() {
+        let Some(version) = get_versions(&project_id, loader, game_version, client)?.into_iter().next() else {
+            continue;
+        };
+        // External dependencies have no project to be a node for
+        for (dependency, dependency_id) in version.dependencies.iter().filter_map(|dependency| Some((dependency, dependency.project_id.clone()?))) {
+            edges.push((project_id.clone(), dependency_id.clone(), dependency.dependency_type.clone()));
+            if nodes.contains(&dependency_id) {
+                continue;
+            }
+            nodes.push(dependency_id.clone());
+            if dependency.dependency_type != "incompatible" {
+                pending.push(dependency_id);
+            }
+        }
+    }
+    let projects = get_projects(&nodes, client)?;
+    let slug = |project_id: &String| projects.get(project_id).map_or(project_id.clone(), |project| project.slug.clone());
+    match graph_format {
+        GraphFormat::Json => print_json(&serde_json::json!({
+            "nodes": nodes.iter().map(|project_id| serde_json::json!({
+                "id": project_id,
+                "slug": slug(project_id),
+                "title": projects.get(project_id).map(|project| &project.title),
+            })).collect::<Vec<_>>(),
+            "edges": edges.iter().map(|(from, to, dependency_type)| serde_json::json!({
+                "from": from,
+                "to": to,
+                "dependency_type": dependency_type,
+            })).collect::<Vec<_>>(),
+        })),
+        GraphFormat::Dot => {
+            println!("digraph dependencies {{");
+            for project_id in nodes.iter() {
+                let title = projects.get(project_id).map_or(project_id.clone(), |project| project.title.clone());
+                println!("    \"{}\" [label=\"{}\"];", slug(project_id), title.replace('"', "\\\""));
+            }
+            for (from, to, dependency_type) in edges.iter() {
+                println!("    \"{}\" -> \"{}\" [label=\"{}\"];", slug(from), slug(to), dependency_type);
+            }
+            println!("}}");
+            Ok(())
+        }
+    }
+}
+
+// Unknown types go last
+fn dependency_type_rank(dependency_type: &str) -> usize {
+    DEPENDENCY_TYPE_ORDER.iter().position(|known| *known == dependency_type).unwrap_or(DEPENDENCY_TYPE_ORDER.len())
+}
+
+fn sort_dependencies(dependencies: &mut [(ProjectDependency, ProjectResponse)], options: &DependencyOptions) {
+    match options.sort_by {
+        Some(DependencySort::Name) => dependencies.sort_by_key(|(_, prj)| prj.title.to_lowercase()),
+        // Stable, within a type they keep the API's order
+        Some(DependencySort::Type) => dependencies.sort_by_key(|(dependency, _)| dependency_type_rank(&dependency.dependency_type)),
+        // The API's order isn't stable, so unless asked not to they're sorted for diffable output
+        None if !options.no_sort => dependencies.sort_by_key(|(dependency, prj)| (dependency_type_rank(&dependency.dependency_type), prj.slug.clone())),
+        None => {}
+    }
+}
+
+// The status is "ok", "no_compatible_version" for a required dependency without a matching
+// build, "external" for one that isn't on Modrinth or "error" when it couldn't be fetched
+fn dependency_entries(resolved: &[(ProjectDependency, ProjectResponse)], unavailable: &HashSet<String>, failed: &[(ProjectDependency, String, String)], external: &[ProjectDependency], include_external: bool) -> Vec<DependencyEntry> {
+    let mut entries: Vec<DependencyEntry> = resolved.iter().map(|(dependency, prj)| DependencyEntry {
+        dependency_type: dependency.dependency_type.clone(),
+        project_id: Some(prj.id.clone()),
+        slug: Some(prj.slug.clone()),
+        title: Some(prj.title.clone()),
+        file_name: None,
+        status: if unavailable.contains(&prj.id) { "no_compatible_version" } else { "ok" },
+        error: None,
+    }).collect();
+    entries.extend(failed.iter().map(|(dependency, project_id, error)| DependencyEntry {
+        dependency_type: dependency.dependency_type.clone(),
+        project_id: Some(project_id.clone()),
+        slug: None,
+        title: None,
+        file_name: None,
+        status: "error",
+        error: Some(error.clone()),
+    }));
+    if include_external {
+        entries.extend(external.iter().map(|dependency| DependencyEntry {
+            dependency_type: dependency.dependency_type.clone(),
+            project_id: None,
+            slug: None,
+            title: None,
+            file_name: Some(dependency.external_name()),
+            status: "external",
+            error: None,
+        }));
+    }
+    entries
+}
+
+fn print_dependencies(dependencies: &[(ProjectDependency, ProjectResponse)]) {
+    for (dependency, project) in dependencies.iter() {
+        println!("{}", terminal_text(&format!("{}: \"{}\" - {}", dependency.dependency_type, project.title, project_handle(&project.slug, &project.id))))
+    }
+}
+
+fn project_info(project: &str, gallery: bool, summary_only: bool, client: &Client) -> Result<(), Box<dyn Error>> {
+    let resp_txt = api_get(client, &format!("/project/{}", project), &[])?;
+    let processed_resp: Result<ProjectResponse, serde_json::Error> = serde_json::from_str(&resp_txt);
 
     let project = match processed_resp {
         Ok(prj) => prj,
         Err(e) => {
-            println!("{}", e);
-            return Err(Box::new(e));
+            return Err(Box::new(RustdrynthError::Parse(format!("Couldn't get the project \"{}\": {}{}", project, e, token_hint()))));
         }
     };
+    if structured_output() {
+        print_structured("project", &project)?;
+        return Ok(());
+    }
+    warn_unapproved(&project);
     
-    println!("{} - {}", project.project_type, project.title);
+    println!("{}", terminal_text(&format!("{} - {}", project_type_label(&project.project_type), project.title)));
+    println!("{}", project_handle(&project.slug, &project.id));
+    if summary_only {
+        println!("Categories: {}", project.categories.join(", "));
+        println!("Downloads: {}", project.downloads);
+        println!("{}", terminal_text(project.description.trim()));
+        return Ok(());
+    }
+    // The short summary, the full markdown body comes after the categories
+    if !project.description.is_empty() {
+        println!("{}", terminal_text(project.description.trim()));
+    }
     for category in project.categories.iter() {
         print!("{}", category);
     }
-    println!("\n\n{}\n", project.body);
+    if gallery {
+        println!("\n");
+        if project.gallery.is_empty() {
+            println!("{}", terminal_text(&format!("{} has no gallery images", project.title)));
+        }
+        for image in project.gallery.iter() {
+            match &image.title {
+                Some(title) => println!("{}", terminal_text(&format!("{}: {}", title, image.url))),
+                None => println!("{}", image.url),
+            }
+        }
+        return Ok(());
+    }
+    println!("\n\n{}\n", terminal_text(&project.body));
 
     Ok(())
 }
 
-fn get_download_link(slug: &str, loader: &str, game_version: &str, client: &Client) -> Result<GameFiles, Box<dyn Error>> {
-    let download_link = format!("https://api.modrinth.com/v2/project/{}/version?loader=[\"{}\"]&game_versions=[\"{}\"]", slug, loader, game_version);
-    let resp = client.get(&download_link).header(USER_AGENT, "https://github.com/Tomyatana/Pydrinth/tree/Rustdrynth").send()?;
-    let resp_txt = resp.text()?;
-    let processed_response: Vec<GameVersion> = match serde_json::from_str(&resp_txt) {
-        Ok(response) => response,
-        Err(e) => {
-            println!("{}", e);
-            return Err(Box::new(e))
+fn download_project(project: &str, options: &DownloadOptions, client: &Client) -> Result<(), Box<dyn Error>> {
+    // Only for the warning, a project that can't be fetched is reported by the version lookup
+    if let Ok(project) = get_project(project, client) {
+        warn_unapproved(&project);
+    }
+    let versions = resolve_download_plan(project, options, client)?;
+    if options.head_check {
+        head_check(&versions, client)?;
+    }
+    if (versions.len() > 1 || options.dry_run) && !confirm_plan(&versions, options)? {
+        return Ok(());
+    }
+    install_plan(versions, 1, Vec::new(), options, client)
+}
+
+// Rejected or withheld projects can still be viewed but their files may be gone
+fn warn_unapproved(project: &ProjectResponse) {
+    if !project.status.is_empty() && project.status != "approved" {
+        warn(&format!("Warning: {} is {} on Modrinth, not approved, its files may not be downloadable", project_handle(&project.slug, &project.id), project.status));
+    }
+}
+
+// The project's version first, followed by its dependencies when they were asked for
+fn resolve_download_plan(project: &str, options: &DownloadOptions, client: &Client) -> Result<Vec<GameVersion>, Box<dyn Error>> {
+    let version = match &options.filename {
+        Some(filename) => get_version_with_file(project, filename, options, client)?,
+        None => match get_version(project, options, client) {
+            Ok(version) => version,
+            // It says which classifier is missing, not just that nothing matched
+            Err(e) if options.classifier != Classifier::Main || !is_not_found(&*e) => return Err(e),
+            Err(_) => {
+                return Err(Box::new(RustdrynthError::NotFound(format!("Couldn't find a {} {} version of {}{}", options.loader, options.game_version, project, token_hint()))));
+            }
+        },
+    };
+    let mut versions = vec![version];
+    if options.with_deps {
+        let plan = dependency_plan(&versions[0], options, client)?;
+        for (required_by, name) in plan.external.iter() {
+            warn(&format!("Warning: {} requires the external dependency {}, which isn't on Modrinth and has to be downloaded manually", required_by, name));
+        }
+        if let (true, Some((required_by, dependency_id))) = (options.strict, plan.missing.first()) {
+            return Err(Box::new(RustdrynthError::NotFound(format!("Couldn't find a {} {} version of {}, required by {}, nothing was downloaded", options.loader, options.game_version, dependency_id, required_by))));
+        }
+        for (_, dependency_id) in plan.missing.iter() {
+            warn(&format!("Warning: couldn't find a {} {} version of the dependency {}, skipping it", options.loader, options.game_version, dependency_id));
+        }
+        let mut dependencies = plan.versions;
+        if options.missing_only {
+            // Any installed version of the dependency counts, not only the one that would be downloaded
+            let installed = installed_project_ids(&target_dir(options).display().to_string(), client)?;
+            dependencies.retain(|dependency| !installed.contains(&dependency.project_id));
+        }
+        versions.extend(dependencies);
+    }
+    if let Some(template) = &options.filename_template {
+        for version in versions.iter_mut() {
+            let project = get_project(&version.project_id, client)?;
+            for file in version.files.iter_mut() {
+                let filename = sanitize_filename(&template.replace("{slug}", &project.slug).replace("{version}", &version.version_number));
+                if !filename.is_empty() {
+                    file.filename = filename;
+                }
+            }
+        }
+    }
+    Ok(versions)
+}
+
+// Swaps anything that isn't allowed in a file name on some platform, or could leave the folder, for "_"
+fn sanitize_filename(filename: &str) -> String {
+    let sanitized: String = filename.chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    sanitized.trim_matches(|c| c == '.' || c == ' ').to_string()
+}
+
+// The hash picks both the version and the file in it, the saved file is hashed again so
+// a mirror serving something else never goes unnoticed
+fn download_by_hash(hash: &str, options: &DownloadOptions, client: &Client) -> Result<(), Box<dyn Error>> {
+    let hash = hash.trim().to_lowercase();
+    let resp_txt = match api_get(client, &format!("/version_file/{}", hash), &[("algorithm", "sha512")]) {
+        Err(e) if is_not_found(&*e) => String::new(),
+        resp_txt => resp_txt?,
+    };
+    let Ok(mut version) = serde_json::from_str::<GameVersion>(&resp_txt) else {
+        return Err(Box::new(RustdrynthError::NotFound(format!("No file on Modrinth has the sha512 hash {}{}", hash, token_hint()))));
+    };
+    version.files.retain(|file| file.hashes.get("sha512") == Some(&hash));
+    let Some(file) = version.files.first().cloned() else {
+        return Err(Box::new(RustdrynthError::NotFound(format!("Version {} doesn't list a file with the sha512 hash {}", version.version_number, hash))));
+    };
+    let target_dir = target_dir(options);
+    fs::create_dir_all(&target_dir)?;
+    ensure_writable(&target_dir)?;
+    let target = target_dir.join(&file.filename);
+    if target.exists() && hash_file(&target)? == hash {
+        status(&format!("{} is already present, skipping it", file.filename));
+        return Ok(());
+    }
+    download_jar(version.files, options.verify, client, &target_dir)?;
+    if hash_file(&target)? != hash {
+        let _ = fs::remove_file(&target);
+        return Err(Box::new(RustdrynthError::Conflict(format!("The downloaded {} doesn't match the sha512 hash {}, it was removed", file.filename, hash))));
+    }
+    verbose(&format!("{} matches the requested hash", file.filename));
+    Ok(())
+}
+
+// The size comes from the headers, so nothing is downloaded. One unreachable file stops
+// the whole plan before any of it is saved
+fn head_check(versions: &[GameVersion], client: &Client) -> Result<(), Box<dyn Error>> {
+    let mut total = 0;
+    for file in versions.iter().filter_map(|version| version.files.first()) {
+        explain("HEAD", &file.url);
+        let resp = match send(prepare(client.head(&file.url), &file.url)).and_then(|resp| resp.error_for_status()) {
+            Ok(resp) => resp,
+            Err(e) => return Err(Box::new(RustdrynthError::Network(format!("{} isn't reachable, nothing was downloaded: {}", file.url, e)))),
+        };
+        let size = resp.headers().get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(file.size);
+        println!("{} is reachable, {}", file.filename, format_bytes(size));
+        total += size;
+    }
+    if versions.len() > 1 {
+        println!("{} in total", format_bytes(total));
+    }
+    Ok(())
+}
+
+// Lists every file of the plan, returns whether the download should go ahead
+fn confirm_plan(versions: &[GameVersion], options: &DownloadOptions) -> Result<bool, Box<dyn Error>> {
+    let files: Vec<(String, u64)> = versions.iter()
+        .filter_map(|version| version.files.first().map(|file| (format!("{} {}", file.filename, version.version_number), file.size)))
+        .collect();
+    confirm_files(&files, &[], options.yes, options.dry_run)
+}
+
+// The files come with their size, the removed ones are jars the download replaces
+fn confirm_files(files: &[(String, u64)], removed: &[String], yes: bool, dry_run: bool) -> Result<bool, Box<dyn Error>> {
+    if !settings().quiet || dry_run {
+        println!("The following files will be downloaded:");
+        for (file, size) in files {
+            println!("  {} ({})", file, format_bytes(*size));
+        }
+        if !removed.is_empty() {
+            println!("The following files will be removed:");
+            for file in removed {
+                println!("  {}", file);
+            }
+        }
+    }
+    if dry_run {
+        println!("Dry run, nothing was downloaded");
+        return Ok(false);
+    }
+    if yes || settings().quiet || confirm(&format!("Download {} files?", files.len()))? {
+        return Ok(true);
+    }
+    println!("Nothing was downloaded");
+    Ok(false)
+}
+
+// The first `requested` versions are the asked for projects and the rest their dependencies,
+// `results` holds the projects that were settled before planning, e.g. without a compatible build
+fn install_plan(versions: Vec<GameVersion>, requested: usize, mut results: Vec<BatchResult>, options: &DownloadOptions, client: &Client) -> Result<(), Box<dyn Error>> {
+    let target_dir = target_dir(options);
+    fs::create_dir_all(&target_dir)?;
+    ensure_writable(&target_dir)?;
+    let batch = versions.len() > 1 || !results.is_empty();
+    let project_ids: Vec<String> = versions.iter().map(|version| version.project_id.clone()).collect();
+    let projects = if batch { get_projects(&project_ids, client)? } else { HashMap::new() };
+    let dependencies = versions.len() - requested;
+    let mut skipped = 0;
+    let mut downloaded = 0;
+    let mut pending = Vec::new();
+    for version in versions {
+        let project = projects.get(&version.project_id).map_or(version.project_id.clone(), |project| project.slug.clone());
+        if let Some(file) = version.files.iter().find(|file| target_dir.join(&file.filename).exists()) {
+            status(&format!("{} is already present, skipping it", file.filename));
+            skipped += 1;
+            results.push(BatchResult { project, project_id: version.project_id, action: "skipped", version: version.version_number, error: None });
+            continue;
+        }
+        if !batch {
+            downloaded += download_jar(version.files, options.verify, client, &target_dir)?;
+            continue;
+        }
+        pending.push((project, version));
+    }
+    // In a batch a failed file doesn't stop the others, the table tells which ones failed
+    let files: Vec<Vec<GameFiles>> = pending.iter().map(|(_, version)| version.files.clone()).collect();
+    let outcomes = run_jobs(files, |files| download_jar(files, options.verify, client, &target_dir).map_err(|e| e.to_string()));
+    let mut failed = Vec::new();
+    for ((project, version), outcome) in pending.into_iter().zip(outcomes) {
+        match outcome {
+            Ok(written) => {
+                downloaded += written;
+                results.push(BatchResult { project, project_id: version.project_id, action: "installed", version: version.version_number, error: None });
+            }
+            Err(e) => {
+                failed.push(FailedDownload { project: project.clone(), project_id: version.project_id.clone(), version: version.version_number.clone(), target_dir: target_dir.clone(), files: version.files, verify: options.verify });
+                results.push(BatchResult { project, project_id: version.project_id, action: "failed", version: version.version_number, error: Some(e) });
+            }
         }
+    }
+    if batch {
+        save_failed_downloads(&failed);
+    }
+    if options.with_deps {
+        let requested = if requested == 1 { String::from("1 mod") } else { format!("{} mods", requested) };
+        status(&format!("{} requested, {} dependencies resolved, {} skipped (already present), {} downloaded", requested, dependencies, skipped, format_bytes(downloaded)));
+    }
+    if batch {
+        print_results(&results)?;
+    }
+    batch_outcome(&results)
+}
+
+fn failed_downloads_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|cache_dir| cache_dir.join("rustdrynth").join("failed.json"))
+}
+
+// Every batch replaces the failures of the previous one, a batch without any removes the file
+fn save_failed_downloads(failed: &[FailedDownload]) {
+    let Some(path) = failed_downloads_path() else {
+        return;
     };
-    for version in processed_response {
-        if version.loaders.contains(&loader.to_string()) {
-            return Ok(version.files.first().unwrap().clone());
+    if failed.is_empty() {
+        let _ = fs::remove_file(&path);
+        return;
+    }
+    let saved = path.parent().map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, serde_json::to_string(failed).unwrap_or_default()));
+    match saved {
+        Ok(_) => status(&format!("{} files failed, run retry-failed to try them again", failed.len())),
+        Err(e) => warn(&format!("Couldn't save the failed downloads to {}: {}", path.display(), e)),
+    }
+}
+
+// Only the files that failed in the last batch are downloaded again, into the folder they
+// were meant for
+fn retry_failed(client: &Client) -> Result<(), Box<dyn Error>> {
+    let failed: Vec<FailedDownload> = failed_downloads_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    if failed.is_empty() {
+        println!("No failed downloads to retry");
+        return Ok(());
+    }
+    status(&format!("Retrying {} failed downloads", failed.len()));
+    let outcomes = run_jobs(failed, |entry| {
+        let outcome = download_jar(entry.files.clone(), entry.verify, client, &entry.target_dir).map_err(|e| e.to_string());
+        (entry, outcome)
+    });
+    let mut results = Vec::new();
+    let mut still_failed = Vec::new();
+    for (entry, outcome) in outcomes {
+        match outcome {
+            Ok(_) => results.push(BatchResult { project: entry.project, project_id: entry.project_id, action: "installed", version: entry.version, error: None }),
+            Err(e) => {
+                results.push(BatchResult { project: entry.project.clone(), project_id: entry.project_id.clone(), action: "failed", version: entry.version.clone(), error: Some(e) });
+                still_failed.push(entry);
+            }
         }
     }
-    Err(Box::new(std::io::Error::new(std::io::ErrorKind::NotFound, "No matching GameFiles found")))
+    save_failed_downloads(&still_failed);
+    print_results(&results)?;
+    batch_outcome(&results)
 }
 
-fn download_jar(game_files: GameFiles, client: &Client, mcdir: bool) -> Result<(), Box<dyn Error>>{
-    println!("Downloading {} from {}", game_files.filename, game_files.url);
-    let resp = client.get(&game_files.url).header(USER_AGENT, "https://github.com/Tomyatana/Pydrinth/tree/Rustdrynth").send()?;
-    if resp.status().is_success() {
-        let bytes = resp.bytes();
-        if !check_for_mods_dir().is_empty() && mcdir {
-                let _ = fs::write(format!("{}/{}", check_for_mods_dir(), game_files.filename), bytes?.as_ref());
-        } else {
-            let _ = fs::write(game_files.filename, bytes?.as_ref());
+// Runs the job on every item with up to --jobs threads, the outcomes keep the order of the items
+fn run_jobs<T: Send, R: Send>(items: Vec<T>, job: impl Fn(T) -> R + Sync) -> Vec<R> {
+    let count = items.len();
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let outcomes = Mutex::new(Vec::with_capacity(count));
+    thread::scope(|scope| {
+        for _ in 0..(settings().jobs as usize).min(count) {
+            scope.spawn(|| loop {
+                let Some((index, item)) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                let outcome = job(item);
+                outcomes.lock().unwrap().push((index, outcome));
+            });
         }
-    } else {
-        println!("Couldn't get file from {}", &game_files.url);
+    });
+    let mut outcomes = outcomes.into_inner().unwrap();
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+fn batch_outcome(results: &[BatchResult]) -> Result<(), Box<dyn Error>> {
+    let failed = results.iter().filter(|result| result.action == "failed").count();
+    if failed > 0 {
+        return Err(Box::new(RustdrynthError::Network(format!("{} of the {} files couldn't be downloaded", failed, results.len()))));
     }
     Ok(())
 }
 
-fn get_project(project_id: &str, client: &Client) -> Result<ProjectResponse, Box<dyn Error>> {
-    let resp = client.get(format!("https://api.modrinth.com/v2/project/{}", project_id)).header(USER_AGENT, "https://github.com/Tomyatana/Pydrinth/tree/Rustdrynth").send()?;
-    let resp_txt = resp.text()?;
-    let processed_response: Result<ProjectResponse, _> = serde_json::from_str(&resp_txt);
-    let project = match processed_response {
-        Ok(prj) => prj,
-        Err(e) => {
-            println!("Couldn't get the project");
-            return Err(Box::new(e));
+// The outcome of every project touched by a batch, as a table or as JSON
+fn print_results(results: &[BatchResult]) -> Result<(), Box<dyn Error>> {
+    if settings().format == OutputFormat::Json {
+        return print_json(&results);
+    }
+    // Without a slug, e.g. for an excluded dependency or an entry saved by an older version, only the id is known
+    let rows: Vec<Vec<String>> = results.iter().map(|result| vec![
+        if result.project_id.is_empty() || result.project == result.project_id { result.project.clone() } else { project_handle(&result.project, &result.project_id) },
+        result.action.to_string(),
+        result.version.clone(),
+        result.error.clone().unwrap_or_default(),
+    ]).collect();
+    print_table(&["Project", "Action", "Version", "Error"], &rows);
+    Ok(())
+}
+
+// Left aligns every column to its widest cell
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
         }
-    };
-    Ok(project)
+    }
+    let format_row = |cells: Vec<&str>| cells.iter().zip(&widths)
+        .map(|(cell, width)| format!("{:width$}", cell, width = width))
+        .collect::<Vec<String>>()
+        .join("  ")
+        .trim_end()
+        .to_string();
+    println!("{}", format_row(headers.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(|cell| cell.as_str()).collect()));
+    }
 }
 
-fn remove_last_char(string: &str, char: char) -> String {
-    if let Some(index) = string.rfind(char) {
-        let mut result = String::with_capacity(string.len() - 1);
-        result.push_str(&string[..index]);
-        result.push_str(&string[index + char.len_utf8()..]);
-        result
+// --output-dir wins over --mcdir, the --organize-by subfolder goes inside whichever is used
+fn target_dir(options: &DownloadOptions) -> PathBuf {
+    let base = if let Some(output_dir) = &options.output_dir {
+        output_dir.clone()
     } else {
-        string.to_string()
+        PathBuf::from(mods_dir(options.mcdir, options.minecraft_path.as_deref()))
+    };
+    match options.organize_by {
+        Some(OrganizeBy::Loader) => base.join(&options.loader),
+        Some(OrganizeBy::GameVersion) => base.join(&options.game_version),
+        None => base,
     }
 }
 
-fn check_for_mods_dir() -> String{
-    let user = whoami::username();
-    let platform = whoami::platform();
-    match platform {
-        Platform::Windows => {
-            if fs::metadata(format!("C:/Users/{}/AppData/Roaming/.minecraft", user)).is_ok() {
-                if fs::metadata(format!("C:/Users/{}/AppData/Roaming/.minecraft/mods", user)).is_ok() {
-                    return String::from(format!("C:/Users/{}/AppData/Roaming/.minecraft/mods", user));
-                } else {
-                    let _ = fs::create_dir(format!("C:/Users/{}/AppData/Roaming/.minecraft/mods", user));
-                    return format!("C:/Users/{}/AppData/Roaming/.minecraft/mods", user).to_string();
-                }
-            }
-        },
-        Platform::Linux => {
-            if fs::metadata(format!("{}/.minecraft", dirs::home_dir().unwrap().display())).is_ok() {
-                if fs::metadata(format!("{}/.minecraft/mods", dirs::home_dir().unwrap().display())).is_ok() {
-                    return String::from(format!("{}/.minecraft/mods", dirs::home_dir().unwrap().display()))
-                } else {
-                    let _ = fs::create_dir(format!("{}/.minecraft/mods", dirs::home_dir().unwrap().display()));
-                    return format!("{}/.minecraft/mods", dirs::home_dir().unwrap().display()).to_string();
-                }
+// Every project is planned on its own so one without a compatible build only gets reported,
+// a dependency shared by several of them or already in the collection is downloaded once
+fn install_collection(collection_id: &str, exclude: &[String], options: &DownloadOptions, client: &Client) -> Result<(), Box<dyn Error>> {
+    let collection: Collection = match serde_json::from_str(&cached_get(&format!("{}/collection/{}", api_v3_url(), collection_id), client)?) {
+        Ok(collection) => collection,
+        Err(e) => return Err(Box::new(RustdrynthError::NotFound(format!("Couldn't get the collection \"{}\": {}{}", collection_id, e, token_hint())))),
+    };
+    status(&format!("{} has {} projects", terminal_text(&collection.name), collection.projects.len()));
+    let projects = get_projects(&collection.projects, client)?;
+    let excluded = excluded_project_ids(exclude, client);
+    let mut requested = Vec::new();
+    let mut dependencies = Vec::new();
+    let mut unavailable = Vec::new();
+    for project_id in collection.projects.iter() {
+        let project = projects.get(project_id).map_or(project_id.clone(), |project| project.slug.clone());
+        if excluded.contains(project_id) {
+            status(&format!("Excluded {}", project));
+            unavailable.push(BatchResult { project, project_id: project_id.clone(), action: "excluded", version: String::new(), error: None });
+            continue;
+        }
+        match resolve_download_plan(project_id, options, client) {
+            Ok(mut plan) => {
+                requested.push(plan.remove(0));
+                dependencies.extend(plan);
             }
+            Err(e) => unavailable.push(BatchResult { project, project_id: project_id.clone(), action: "unavailable", version: String::new(), error: Some(e.to_string()) }),
         }
-        _ => return "".to_string()
     }
-    "".to_string()
+    let mut seen: HashSet<String> = requested.iter().map(|version| version.project_id.clone()).collect();
+    dependencies.retain(|version| seen.insert(version.project_id.clone()));
+    // An excluded dependency is left out even if a project of the collection requires it
+    dependencies.retain(|version| {
+        if !excluded.contains(&version.project_id) {
+            return true;
+        }
+        status(&format!("Excluded the dependency {}", version.project_id));
+        unavailable.push(BatchResult { project: version.project_id.clone(), project_id: version.project_id.clone(), action: "excluded", version: version.version_number.clone(), error: None });
+        false
+    });
+    if requested.is_empty() {
+        print_results(&unavailable)?;
+        return Err(Box::new(RustdrynthError::NotFound(format!("None of the projects of {} have a {} {} version", collection.name, options.loader, options.game_version))));
+    }
+    let count = requested.len();
+    let mut versions = requested;
+    versions.extend(dependencies);
+    if !confirm_plan(&versions, options)? {
+        return Ok(());
+    }
+    install_plan(versions, count, unavailable, options, client)
+}
+
+// Picks the hit whose slug is the query, falling back to the top search result
+fn add_mod(query: &str, options: &DownloadOptions, client: &Client) -> Result<(), Box<dyn Error>> {
+    let game_versions: Vec<String> = std::iter::once(options.game_version.clone()).filter(|game_version| game_version != ANY_GAME_VERSION).collect();
+    let hits = fetch_hits(query, &adapt_to_facet(&["mod".to_string()], vec![options.loader.clone()], &game_versions), SearchSort::Relevance, DEFAULT_SEARCH_LIMIT, client)?.hits;
+    let chosen = match hits.iter().find(|hit| hit.slug == query.trim()).or(hits.first()) {
+        Some(chosen) => chosen,
+        None => return Err(Box::new(RustdrynthError::NotFound(format!("Couldn't find any {} {} mods matching \"{}\"", options.loader, options.game_version, query)))),
+    };
+    println!("{}", terminal_text(&format!("\"{}\" : {}", chosen.title, project_handle(&chosen.slug, &chosen.project_id))));
+    println!("{}\n", terminal_text(&chosen.description));
+    // Unlike download the pick is always confirmed, even when it's a single file
+    let versions = resolve_download_plan(&chosen.slug, options, client)?;
+    if !confirm_plan(&versions, options)? {
+        return Ok(());
+    }
+    install_plan(versions, 1, Vec::new(), options, client)
+}
+
+#[cfg(feature = "tui")]
+#[derive(Default)]
+struct Browser {
+    query: String,
+    editing: bool,
+    hits: Vec<Hit>,
+    selected: ratatui::widgets::ListState,
+    info: Option<String>,
+    message: String,
+}
+
+// The terminal is given back before downloading, so the usual listing and confirmation
+// of the files is printed as with add
+#[cfg(feature = "tui")]
+fn browse(options: &DownloadOptions, client: &Client) -> Result<(), Box<dyn Error>> {
+    let mut terminal = ratatui::init();
+    let mut browser = Browser { editing: true, message: "Type a query and press Enter".to_string(), ..Browser::default() };
+    let picked = browse_loop(&mut terminal, &mut browser, options, client);
+    ratatui::restore();
+    let Some(slug) = picked? else {
+        return Ok(());
+    };
+    let versions = resolve_download_plan(&slug, options, client)?;
+    if !confirm_plan(&versions, options)? {
+        return Ok(());
+    }
+    install_plan(versions, 1, Vec::new(), options, client)
+}
+
+// Returns the slug of the mod to download, if one was picked
+#[cfg(feature = "tui")]
+fn browse_loop(terminal: &mut ratatui::DefaultTerminal, browser: &mut Browser, options: &DownloadOptions, client: &Client) -> Result<Option<String>, Box<dyn Error>> {
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+    loop {
+        terminal.draw(|frame| draw_browser(frame, browser))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if browser.editing {
+            match key.code {
+                KeyCode::Char(c) => browser.query.push(c),
+                KeyCode::Backspace => {
+                    browser.query.pop();
+                }
+                KeyCode::Esc => browser.editing = false,
+                KeyCode::Enter => {
+                    browser.editing = false;
+                    browser.info = None;
+                    browser.message = format!("Searching for \"{}\"...", browser.query);
+                    terminal.draw(|frame| draw_browser(frame, browser))?;
+                    let game_versions: Vec<String> = std::iter::once(options.game_version.clone()).filter(|game_version| game_version != ANY_GAME_VERSION).collect();
+                    match fetch_hits(&browser.query, &adapt_to_facet(&["mod".to_string()], vec![options.loader.clone()], &game_versions), SearchSort::Relevance, TUI_SEARCH_LIMIT, client) {
+                        Ok(response) => {
+                            browser.message = format!("{} of {} mods, Enter for info, d to download, / to search, q to quit", response.hits.len(), response.total_hits);
+                            browser.hits = response.hits;
+                            browser.selected.select(if browser.hits.is_empty() { None } else { Some(0) });
+                        }
+                        Err(e) => browser.message = format!("Couldn't search: {}", e),
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') => return Ok(None),
+            KeyCode::Esc if browser.info.is_some() => browser.info = None,
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('/') => browser.editing = true,
+            KeyCode::Down | KeyCode::Char('j') => browser.selected.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => browser.selected.select_previous(),
+            KeyCode::Enter => {
+                if let Some(hit) = browser.selected.selected().and_then(|index| browser.hits.get(index)) {
+                    browser.info = match get_project(&hit.slug, client) {
+                        Ok(project) => Some(project.body),
+                        Err(e) => Some(format!("Couldn't get the project: {}", e)),
+                    };
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(hit) = browser.selected.selected().and_then(|index| browser.hits.get(index)) {
+                    return Ok(Some(hit.slug.clone()));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+fn draw_browser(frame: &mut ratatui::Frame, browser: &mut Browser) {
+    use ratatui::{layout::{Constraint, Layout}, style::{Modifier, Style}, widgets::{Block, List, Paragraph, Wrap}};
+    let [search_area, results_area, message_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let search_title = if browser.editing { "Search (Enter to search, Esc to browse)" } else { "Search (/ to edit)" };
+    frame.render_widget(Paragraph::new(browser.query.as_str()).block(Block::bordered().title(search_title)), search_area);
+    match &browser.info {
+        Some(info) => frame.render_widget(Paragraph::new(info.as_str()).wrap(Wrap { trim: false }).block(Block::bordered().title("Info (Esc to go back)")), results_area),
+        None => {
+            let items: Vec<String> = browser.hits.iter().map(|hit| format!("{} ({}) — {}", hit.title, hit.slug, hit.description)).collect();
+            let list = List::new(items).block(Block::bordered().title("Results")).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(list, results_area, &mut browser.selected);
+        }
+    }
+    frame.render_widget(Paragraph::new(browser.message.as_str()), message_area);
+}
+
+fn read_mod_list(projects: &[String], file: Option<&Path>) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut projects = projects.to_vec();
+    if let Some(file) = file {
+        projects.extend(fs::read_to_string(file)?.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string()));
+    }
+    if projects.is_empty() {
+        return Err(Box::new(RustdrynthError::InvalidInput("No mods to check, list them or pass a --file".to_string())));
+    }
+    Ok(projects)
+}
+
+// Checks the newest version of every mod of the set for the loader and game version,
+// without downloading anything
+fn check_mod_set(projects: &[String], loader: &str, game_version: &str, client: &Client) -> Result<(), Box<dyn Error>> {
+    let mut problems = Vec::new();
+    let mut members: Vec<(ProjectResponse, GameVersion)> = Vec::new();
+    for project in projects {
+        let project = get_project(project, client)?;
+        match get_versions(&project.id, loader, game_version, client)?.into_iter().next() {
+            Some(version) => members.push((project, version)),
+            None => problems.push(format!("{} has no {} {} version", project.title, loader, game_version)),
+        }
+    }
+    let titles: HashMap<&String, &String> = members.iter().map(|(project, _)| (&project.id, &project.title)).collect();
+    let mut missing = Vec::new();
+    let mut incompatible_pairs = HashSet::new();
+    for (project, version) in members.iter() {
+        for dependency in version.dependencies.iter() {
+            let Some(dependency_id) = dependency.project_id.clone() else {
+                if dependency.dependency_type == "required" {
+                    problems.push(format!("{} requires the external {}, which has to be obtained manually", project.title, dependency.external_name()));
+                }
+                continue;
+            };
+            match (dependency.dependency_type.as_str(), titles.get(&dependency_id)) {
+                // Reported once per pair, even if both mods declare it
+                ("incompatible", Some(other)) => {
+                    let pair = if project.id < dependency_id { (project.id.clone(), dependency_id) } else { (dependency_id, project.id.clone()) };
+                    if incompatible_pairs.insert(pair) {
+                        problems.push(format!("{} is incompatible with {}", project.title, other));
+                    }
+                }
+                ("required", None) => missing.push((project.title.clone(), dependency_id)),
+                _ => {}
+            }
+        }
+    }
+    let missing_ids: Vec<String> = missing.iter().map(|(_, project_id)| project_id.clone()).collect();
+    let missing_projects = get_projects(&missing_ids, client)?;
+    for (title, project_id) in missing {
+        let dependency = missing_projects.get(&project_id).map_or(project_id, |project| project.title.clone());
+        problems.push(format!("{} requires {}, which isn't in the set", title, dependency));
+    }
+    if problems.is_empty() {
+        println!("Pass: the {} mods work together for {} {}", projects.len(), loader, game_version);
+        return Ok(());
+    }
+    println!("Fail: found {} problems", problems.len());
+    for problem in problems.iter() {
+        println!("  {}", terminal_text(problem));
+    }
+    Err(Box::new(RustdrynthError::Conflict(format!("The set has {} problems", problems.len()))))
+}
+
+fn confirm(prompt: &str) -> Result<bool, Box<dyn Error>> {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+// Shared by `dependencies` and `download --with-deps`, so running one after the other only
+// resolves the dependencies once. Plans are kept for a few minutes since versions change often
+fn dependency_plan(version: &GameVersion, options: &DownloadOptions, client: &Client) -> Result<DependencyPlan, Box<dyn Error>> {
+    let key = options.plan_cache_key(version, &cache_scope(api_url(), settings().token.as_deref()));
+    if let Some(key) = &key {
+        if let Some(plan) = plan_cache().lock().unwrap().get(key).cloned() {
+            verbose(&format!("Using the dependencies of {} {} resolved {}s ago", version.project_id, version.version_number, Utc::now().timestamp() - plan.saved));
+            return Ok(plan);
+        }
+    }
+    let mut plan = DependencyPlan { versions: Vec::new(), missing: Vec::new(), external: Vec::new(), saved: Utc::now().timestamp() };
+    let mut seen = HashSet::from([version.project_id.clone()]);
+    resolve_required_dependencies(version, &mut seen, &mut plan, &mut |dependency_id| get_version(dependency_id, options, client))?;
+    if let Some(key) = &key {
+        remember_plan(key, &plan);
+    }
+    Ok(plan)
+}
+
+// Picks the version of a dependency by its project id
+type VersionLookup<'a> = dyn FnMut(&str) -> Result<GameVersion, Box<dyn Error>> + 'a;
+
+// Walks the required dependencies recursively, `seen` keeps shared dependencies from being resolved twice
+fn resolve_required_dependencies(version: &GameVersion, seen: &mut HashSet<String>, plan: &mut DependencyPlan, lookup: &mut VersionLookup) -> Result<(), Box<dyn Error>> {
+    for dependency in version.dependencies.iter().filter(|dependency| dependency.dependency_type == "required") {
+        let Some(dependency_id) = &dependency.project_id else {
+            plan.external.push((version.project_id.clone(), dependency.external_name()));
+            continue;
+        };
+        if !seen.insert(dependency_id.clone()) {
+            continue;
+        }
+        match lookup(dependency_id) {
+            Ok(dependency_version) => {
+                let index = plan.versions.len();
+                resolve_required_dependencies(&dependency_version, seen, plan, lookup)?;
+                // Nested dependencies come right after the one requiring them
+                plan.versions.insert(index, dependency_version);
+            }
+            Err(e) if is_not_found(&*e) => plan.missing.push((version.project_id.clone(), dependency_id.clone())),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// Anything cached across runs is only reused against the same API with the same token,
+// which is hashed so it never ends up in the cache files
+fn cache_scope(api_url: &str, token: Option<&str>) -> String {
+    match token {
+        Some(token) => format!("{}#{}", api_url, &format!("{:x}", Sha512::digest(token))[..16]),
+        None => api_url.to_string(),
+    }
+}
+
+// Only an answer saying there's nothing makes a dependency missing, a failed request isn't one
+fn is_not_found(error: &(dyn Error + 'static)) -> bool {
+    matches!(error.downcast_ref::<RustdrynthError>(), Some(RustdrynthError::NotFound(_)))
+        || error.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::NotFound)
+}
+
+// Looks through every matching version for the file, the returned version only keeps that file
+fn get_version_with_file(slug: &str, filename: &str, options: &DownloadOptions, client: &Client) -> Result<GameVersion, Box<dyn Error>> {
+    for mut version in get_versions(slug, &options.loader, options.version_filter(), client)? {
+        if let Some(file) = version.files.iter().find(|file| file.filename == filename).cloned() {
+            version.files = vec![file];
+            return Ok(version);
+        }
+    }
+    Err(Box::new(RustdrynthError::NotFound(format!("None of the {} {} versions of {} have a file named {}", options.loader, options.game_version, slug, filename))))
+}
+
+fn get_version(slug: &str, options: &DownloadOptions, client: &Client) -> Result<GameVersion, Box<dyn Error>> {
+    let loader = &options.loader;
+    let mut candidates = get_versions(slug, loader, options.version_filter(), client)?;
+    if (options.featured_only || options.select == VersionSelect::Featured) && !candidates.is_empty() {
+        if candidates.iter().any(|version| version.featured) {
+            candidates.retain(|version| version.featured);
+        } else {
+            warn(&format!("{} has no featured {} {} versions, using the latest one", slug, loader, options.game_version));
+        }
+    }
+    let had_candidates = !candidates.is_empty();
+    for version in candidates.iter_mut() {
+        version.files.retain(|file| file_classifier(&file.filename) == options.classifier);
+    }
+    candidates.retain(|version| !version.files.is_empty());
+    if had_candidates && candidates.is_empty() && options.classifier != Classifier::Main {
+        let classifier = options.classifier.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+        return Err(Box::new(RustdrynthError::NotFound(format!("No {} {} version of {} ships a {} jar", loader, options.game_version, slug, classifier))));
+    }
+    if let Some(extension) = &options.extension {
+        let extension = format!(".{}", extension.trim_start_matches('.').to_lowercase());
+        for version in candidates.iter_mut() {
+            version.files.retain(|file| file.filename.to_lowercase().ends_with(&extension));
+        }
+        candidates.retain(|version| !version.files.is_empty());
+    }
+    // Replaces the primary-first order, the other files stay behind as alternates
+    if let Some(sort_files) = options.sort_files {
+        for version in candidates.iter_mut() {
+            match sort_files {
+                FileSort::SizeAsc => version.files.sort_by_key(|file| file.size),
+                FileSort::SizeDesc => version.files.sort_by_key(|file| std::cmp::Reverse(file.size)),
+                FileSort::Name => version.files.sort_by_key(|file| file.filename.to_lowercase()),
+            }
+        }
+    }
+    // The versions come newest first, so ties in the game version count go to the newest
+    let picked = match options.select {
+        VersionSelect::Widest => candidates.iter().enumerate()
+            .max_by_key(|(index, version)| (version.game_versions.len(), std::cmp::Reverse(*index)))
+            .map(|(index, _)| index),
+        _ => (!candidates.is_empty()).then_some(0),
+    };
+    let Some(version) = picked.map(|index| candidates.swap_remove(index)) else {
+        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::NotFound, "No matching GameFiles found")));
+    };
+    match options.select {
+        VersionSelect::Newest if options.featured_only && version.featured => status(&format!("Picked {} of {}, the newest featured version", version.version_number, slug)),
+        VersionSelect::Newest => status(&format!("Picked {} of {}, the newest matching version", version.version_number, slug)),
+        VersionSelect::Widest => status(&format!("Picked {} of {}, it supports the most game versions ({})", version.version_number, slug, version.game_versions.len())),
+        VersionSelect::Featured if version.featured => status(&format!("Picked {} of {}, the newest featured version", version.version_number, slug)),
+        VersionSelect::Featured => status(&format!("Picked {} of {}, the newest version since none are featured", version.version_number, slug)),
+    }
+    Ok(version)
+}
+
+// Sources and javadoc jars are told apart by the suffix maven gives them, e.g. "sodium-0.5.8-sources.jar"
+fn file_classifier(filename: &str) -> Classifier {
+    let stem = Path::new(filename).file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_lowercase();
+    if stem.ends_with("-sources") {
+        Classifier::Sources
+    } else if stem.ends_with("-javadoc") {
+        Classifier::Javadoc
+    } else {
+        Classifier::Main
+    }
+}
+
+// Every version of the project for the loader and game version, newest first
+fn get_versions(slug: &str, loader: &str, game_version: &str, client: &Client) -> Result<Vec<GameVersion>, Box<dyn Error>> {
+    let resp_txt = get_version_list(slug, loader, game_version, client)?;
+    let processed_response: Vec<GameVersion> = match serde_json::from_str(&resp_txt) {
+        Ok(response) => response,
+        Err(e) => {
+            return Err(Box::new(e))
+        }
+    };
+    Ok(processed_response.into_iter()
+        .filter(|version| version.loaders.iter().any(|version_loader| version_loader == loader) && !version.files.is_empty())
+        .map(|mut version| {
+            // Stable, the other files keep the API's order behind the primary one
+            version.files.sort_by_key(|file| !file.primary);
+            if version.loaders.len() > 1 {
+                version.files.sort_by_key(|file| loader_file_rank(&file.filename, loader, &version.loaders));
+            }
+            version
+        })
+        .collect())
+}
+
+// Versions for several loaders may ship a jar per loader, the files don't say which loader
+// they're for so it's guessed from the name. Whole words are compared, so a neoforge jar
+// doesn't count as a forge one
+fn loader_file_rank(filename: &str, loader: &str, loaders: &[String]) -> u8 {
+    let filename = filename.to_lowercase();
+    let words: Vec<&str> = filename.split(|c: char| !c.is_ascii_alphanumeric()).collect();
+    if words.contains(&loader.to_lowercase().as_str()) {
+        0
+    } else if loaders.iter().any(|other| words.contains(&other.to_lowercase().as_str())) {
+        2
+    } else {
+        1
+    }
+}
+
+// The files are tried in order, so a failing primary file falls back to the version's other files
+// Returns how many bytes were written
+fn download_jar(game_files: Vec<GameFiles>, verify: bool, client: &Client, target_dir: &Path) -> Result<u64, Box<dyn Error>>{
+    let mut broke_off = None;
+    for (index, game_file) in game_files.iter().enumerate() {
+        status(&format!("Downloading {} ({}) from {}", game_file.filename, format_bytes(game_file.size), game_file.url));
+        let target = target_dir.join(&game_file.filename);
+        // The body is streamed into a .part file first, so an interrupted download never looks like a finished jar
+        let part = PathBuf::from(format!("{}.part", target.display()));
+        PARTIAL_DOWNLOADS.lock().unwrap().push(part.clone());
+        let written = match fetch_part(game_file, &part, client) {
+            Ok(Some(written)) => written,
+            Ok(None) => {
+                PARTIAL_DOWNLOADS.lock().unwrap().retain(|other| *other != part);
+                warn(&format!("Couldn't get file from {}", &game_file.url));
+                continue;
+            }
+            // Kept for the next run to resume, the alternate files are still tried
+            Err(PartError::Transfer(e)) => {
+                PARTIAL_DOWNLOADS.lock().unwrap().retain(|other| *other != part);
+                let message = format!("Couldn't finish downloading {}, running it again resumes {}: {}", game_file.filename, part.display(), e);
+                warn(&message);
+                broke_off = Some(message);
+                continue;
+            }
+            Err(PartError::Write(e)) => {
+                let _ = fs::remove_file(&part);
+                PARTIAL_DOWNLOADS.lock().unwrap().retain(|other| *other != part);
+                return Err(save_error(&part, e));
+            }
+        };
+        if verify && is_archive(&game_file.filename) && !has_zip_magic(&part) {
+            let _ = fs::remove_file(&part);
+            PARTIAL_DOWNLOADS.lock().unwrap().retain(|other| *other != part);
+            warn(&format!("{} from {} isn't a valid zip, it's likely an error page or truncated, removed it", game_file.filename, game_file.url));
+            continue;
+        }
+        let renamed = fs::rename(&part, &target);
+        PARTIAL_DOWNLOADS.lock().unwrap().retain(|other| *other != part);
+        if let Err(e) = renamed {
+            let _ = fs::remove_file(&part);
+            return Err(save_error(&target, e));
+        }
+        if index > 0 {
+            status(&format!("Downloaded {} from the version's alternate files", game_file.filename));
+        } else {
+            status(&format!("Downloaded {}", game_file.filename));
+        }
+        return Ok(written);
+    }
+    match broke_off {
+        Some(message) => Err(Box::new(RustdrynthError::Network(message))),
+        None => Err(Box::new(io::Error::new(io::ErrorKind::NotFound, "None of the version's files could be downloaded"))),
+    }
+}
+
+// Whether fetching a file failed on the way from the server or while writing it to disk
+enum PartError {
+    Transfer(io::Error),
+    Write(io::Error),
+}
+
+// Resumes what's already in the .part with a range request when the server takes them, a
+// connection that drops mid-file is resumed the same way up to --retries times.
+// Returns how many bytes the file has, None means it couldn't be requested at all
+fn fetch_part(game_file: &GameFiles, part: &Path, client: &Client) -> Result<Option<u64>, PartError> {
+    // A .part that's already as long as the file can't be resumed, it's from another file
+    let mut offset = fs::metadata(part).map_or(0, |metadata| metadata.len());
+    if offset >= game_file.size || (offset > 0 && !accepts_ranges(&game_file.url, client)) {
+        offset = 0;
+    }
+    let mut resumed = false;
+    let mut attempt = 0;
+    loop {
+        let mut request = client.get(&game_file.url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+        explain("GET", &game_file.url);
+        // Redirects are already followed here, an html page at the end of them is never a jar
+        let mut resp = match send(prepare(request, &game_file.url)) {
+            Ok(resp) if resp.status().is_success() && !is_html(&resp) => resp,
+            _ => return Ok(None),
+        };
+        let ranges = resp.headers().get(ACCEPT_RANGES).is_some_and(|value| value == "bytes");
+        // A server that ignores the range sends the whole file again
+        let mut file = if offset > 0 && resp.status() == StatusCode::PARTIAL_CONTENT {
+            status(&format!("Resuming {} at {}", game_file.filename, format_bytes(offset)));
+            resumed = true;
+            fs::OpenOptions::new().append(true).open(part).map_err(PartError::Write)?
+        } else {
+            offset = 0;
+            fs::File::create(part).map_err(PartError::Write)?
+        };
+        let bar = download_bar(game_file);
+        bar.set_position(offset);
+        let copied = copy_body(&mut bar.wrap_read(&mut resp), &mut file);
+        bar.finish_and_clear();
+        match copied {
+            // Appending to a stale .part would go unnoticed otherwise, so it starts over once
+            Ok(_) if resumed && game_file.hashes.get("sha512").is_some_and(|expected| hash_file(part).is_ok_and(|hash| hash != *expected)) => {
+                warn(&format!("The resumed {} doesn't match its sha512 hash, downloading it again from the start", game_file.filename));
+                offset = 0;
+                resumed = false;
+            }
+            Ok(_) => return fs::metadata(part).map(|metadata| Some(metadata.len())).map_err(PartError::Write),
+            Err(PartError::Transfer(_)) if ranges && attempt < settings().retries => {
+                attempt += 1;
+                offset = fs::metadata(part).map_err(PartError::Write)?.len();
+                status(&format!("The download of {} broke off at {}, resuming it ({}/{})", game_file.filename, format_bytes(offset), attempt, settings().retries));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Like io::copy, but keeps a body that broke off apart from a disk that can't take it
+fn copy_body(body: &mut impl Read, file: &mut fs::File) -> Result<u64, PartError> {
+    let mut buffer = [0; 64 * 1024];
+    let mut copied = 0;
+    loop {
+        let read = match body.read(&mut buffer) {
+            Ok(0) => return Ok(copied),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(PartError::Transfer(e)),
+        };
+        file.write_all(&buffer[..read]).map_err(PartError::Write)?;
+        copied += read as u64;
+    }
+}
+
+fn accepts_ranges(url: &str, client: &Client) -> bool {
+    explain("HEAD", url);
+    send(prepare(client.head(url), url))
+        .is_ok_and(|resp| resp.headers().get(ACCEPT_RANGES).is_some_and(|value| value == "bytes"))
+}
+
+// Jars, modpacks and most resource packs are zips
+fn is_archive(filename: &str) -> bool {
+    Path::new(filename).extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| ["jar", "zip", "mrpack"].contains(&extension.to_lowercase().as_str()))
+}
+
+fn has_zip_magic(path: &Path) -> bool {
+    let mut magic = [0; 4];
+    fs::File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && magic == *b"PK\x03\x04"
+}
+
+fn save_error(path: &Path, error: io::Error) -> Box<dyn Error> {
+    Box::new(RustdrynthError::Io(format!("Couldn't save {}: {}", path.display(), error)))
+}
+
+fn is_html(resp: &Response) -> bool {
+    resp.headers().get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"))
+}
+
+// Writing a throwaway file is the only reliable check, permissions alone miss read-only
+// mounts and ACLs. Done before fetching anything so no download is wasted on it
+fn ensure_writable(dir: &Path) -> Result<(), Box<dyn Error>> {
+    let probe = dir.join(".rustdrynth-write-check");
+    match fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) => Err(Box::new(RustdrynthError::Io(format!("Can't write to {}: {}", dir.display(), e)))),
+    }
+}
+
+// Several downloads can be in flight with --jobs
+static PARTIAL_DOWNLOADS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+fn handle_interrupt() {
+    let parts = std::mem::take(&mut *PARTIAL_DOWNLOADS.lock().unwrap());
+    if parts.is_empty() {
+        eprintln!("\nInterrupted");
+    }
+    for part in parts {
+        let _ = fs::remove_file(&part);
+        eprintln!("\nInterrupted, removed the partial download {}", part.display());
+    }
+    process::exit(130);
+}
+
+fn install_modpack(file: &Path, output_dir: &Path, exclude: &[String], yes: bool, client: &Client) -> Result<(), Box<dyn Error>> {
+    let mut archive = ZipArchive::new(fs::File::open(file)?)?;
+    let index = read_modpack_index(file)?;
+    fs::create_dir_all(output_dir)?;
+    ensure_writable(output_dir)?;
+    status(&format!("Installing {} into {}", index.name, output_dir.display()));
+    for (dependency, version) in &index.dependencies {
+        status(&format!("Requires {} {}", dependency, version));
+    }
+    // Files that aren't on Modrinth have no project id, they can only be excluded by name
+    let file_name = |entry: &ModpackFile| Path::new(&entry.path).file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+    let file_names: HashSet<String> = index.files.iter().map(file_name).collect();
+    let excluded = excluded_project_ids(exclude.iter().filter(|project| !file_names.contains(*project)), client);
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for entry in &index.files {
+        let file_name = file_name(entry);
+        let project_id = entry.downloads.iter().find_map(|url| modrinth_cdn_ids(url)).map(|(project_id, _)| project_id);
+        if exclude.contains(&file_name) || project_id.is_some_and(|project_id| excluded.contains(&project_id)) {
+            status(&format!("Excluded {}", entry.path));
+            skipped += 1;
+            continue;
+        }
+        if entry.env.as_ref().is_some_and(|env| env.client == "unsupported") {
+            verbose(&format!("Skipping {}, it's server only", entry.path));
+            continue;
+        }
+        let path = Path::new(&entry.path);
+        if path.is_absolute() || path.components().any(|component| component == Component::ParentDir) {
+            return Err(Box::new(RustdrynthError::InvalidInput(format!("The modpack file {} points outside of the instance", entry.path))));
+        }
+        entries.push(entry);
+    }
+    let files: Vec<(String, u64)> = entries.iter().map(|entry| (entry.path.clone(), entry.file_size)).collect();
+    if !entries.is_empty() && !confirm_files(&files, &[], yes, false)? {
+        return Ok(());
+    }
+    let installed = entries.len();
+    let mut from_mirrors = 0;
+    for outcome in run_jobs(entries, |entry| download_modpack_file(entry, &output_dir.join(&entry.path), client).map_err(RustdrynthError::from)) {
+        if outcome? > 0 {
+            from_mirrors += 1;
+        }
+    }
+    extract_overrides(&mut archive, output_dir)?;
+    status(&format!("Installed {} files, {} of them from a fallback mirror", installed, from_mirrors));
+    if skipped > 0 {
+        status(&format!("Excluded {} files", skipped));
+    }
+    Ok(())
+}
+
+// Either a .mrpack or the modrinth.index.json taken out of one
+fn read_modpack_index(file: &Path) -> Result<ModpackIndex, Box<dyn Error>> {
+    if file.extension().is_some_and(|extension| extension == "json") {
+        return Ok(serde_json::from_reader(fs::File::open(file)?)?);
+    }
+    let mut archive = ZipArchive::new(fs::File::open(file)?)?;
+    let index = serde_json::from_reader(archive.by_name("modrinth.index.json")?)?;
+    Ok(index)
+}
+
+// Nothing is downloaded, a hash Modrinth doesn't know anymore is looked up through the
+// project and version in its download url to tell what changed
+fn validate_modpack(file: &Path, client: &Client) -> Result<(), Box<dyn Error>> {
+    let index = read_modpack_index(file)?;
+    let hashes: Vec<&String> = index.files.iter().map(|entry| &entry.hashes.sha512).collect();
+    let known = get_versions_from_hashes(&hashes, client)?;
+    let mut drift = Vec::new();
+    let mut unchecked = 0;
+    for entry in index.files.iter().filter(|entry| !known.contains_key(&entry.hashes.sha512)) {
+        let Some((project_id, version_id)) = entry.downloads.iter().find_map(|url| modrinth_cdn_ids(url)) else {
+            verbose(&format!("{} isn't hosted on Modrinth, it can't be checked", entry.path));
+            unchecked += 1;
+            continue;
+        };
+        if get_project(&project_id, client).is_err() {
+            drift.push(format!("{}: the project {} no longer exists", entry.path, project_id));
+            continue;
+        }
+        match api_get(client, &format!("/version/{}", version_id), &[]) {
+            Ok(resp_txt) => {
+                let version: GameVersion = serde_json::from_str(&resp_txt)?;
+                drift.push(format!("{}: the file of version {} changed, its hash doesn't match the pinned one", entry.path, version.version_number));
+            }
+            Err(e) if is_not_found(&*e) => drift.push(format!("{}: the version {} was deleted", entry.path, version_id)),
+            Err(e) => return Err(e),
+        }
+    }
+    if unchecked > 0 {
+        status(&format!("{} files aren't hosted on Modrinth and weren't checked", unchecked));
+    }
+    if drift.is_empty() {
+        println!("{} is up to date with Modrinth, all {} pinned files still exist", index.name, index.files.len() - unchecked);
+        return Ok(());
+    }
+    println!("Found {} changes since {} was made", drift.len(), index.name);
+    for change in drift.iter() {
+        println!("  {}", terminal_text(change));
+    }
+    Err(Box::new(RustdrynthError::Conflict(format!("{} files of {} changed upstream", drift.len(), index.name))))
+}
+
+// The jars of a set keyed by project id, or by file name for jars Modrinth doesn't know,
+// with their version number, or the start of their hash for the unknown ones
+fn read_mod_set(source: &Path, client: &Client) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let jars: Vec<(String, String)> = if source.is_dir() {
+        jar_hashes(&source.display().to_string())?.into_iter()
+            .map(|(path, hash)| (path.file_name().unwrap_or_default().to_string_lossy().to_string(), hash))
+            .collect()
+    } else {
+        read_modpack_index(source)?.files.into_iter()
+            .filter(|entry| entry.path.ends_with(".jar"))
+            .map(|entry| (Path::new(&entry.path).file_name().unwrap_or_default().to_string_lossy().to_string(), entry.hashes.sha512))
+            .collect()
+    };
+    let hashes: Vec<&String> = jars.iter().map(|(_, hash)| hash).collect();
+    let versions = get_versions_from_hashes(&hashes, client)?;
+    Ok(jars.iter().map(|(filename, hash)| match versions.get(hash) {
+        Some(version) => (version.project_id.clone(), version.version_number.clone()),
+        None => (filename.clone(), hash.chars().take(12).collect()),
+    }).collect())
+}
+
+fn diff_mod_sets(old: &Path, new: &Path, client: &Client) -> Result<(), Box<dyn Error>> {
+    let old_set = read_mod_set(old, client)?;
+    let new_set = read_mod_set(new, client)?;
+    // The unknown jars are keyed by their file name, which isn't an id to look up
+    let project_ids: Vec<String> = old_set.keys().chain(new_set.keys()).filter(|key| !key.ends_with(".jar")).cloned().collect();
+    let projects = get_projects(&project_ids, client)?;
+    let name = |key: &String| projects.get(key).map_or(key.clone(), |project| project.slug.clone());
+    let mut added: Vec<(String, &String)> = new_set.iter().filter(|(key, _)| !old_set.contains_key(*key)).map(|(key, version)| (name(key), version)).collect();
+    let mut removed: Vec<(String, &String)> = old_set.iter().filter(|(key, _)| !new_set.contains_key(*key)).map(|(key, version)| (name(key), version)).collect();
+    let mut changed: Vec<(String, &String, &String)> = old_set.iter()
+        .filter_map(|(key, from)| new_set.get(key).filter(|to| *to != from).map(|to| (name(key), from, to)))
+        .collect();
+    added.sort();
+    removed.sort();
+    changed.sort();
+    if settings().format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "added": added.iter().map(|(project, version)| serde_json::json!({ "project": project, "version": version })).collect::<Vec<_>>(),
+            "removed": removed.iter().map(|(project, version)| serde_json::json!({ "project": project, "version": version })).collect::<Vec<_>>(),
+            "changed": changed.iter().map(|(project, from, to)| serde_json::json!({ "project": project, "from": from, "to": to })).collect::<Vec<_>>(),
+        }));
+    }
+    if added.is_empty() && removed.is_empty() && changed.is_empty() {
+        println!("The two sets have the same mods and versions");
+        return Ok(());
+    }
+    for (project, version) in added.iter() {
+        println!("+ {} {}", project, version);
+    }
+    for (project, version) in removed.iter() {
+        println!("- {} {}", project, version);
+    }
+    for (project, from, to) in changed.iter() {
+        println!("~ {} {} -> {}", project, from, to);
+    }
+    println!("{} added, {} removed, {} changed", added.len(), removed.len(), changed.len());
+    Ok(())
+}
+
+// cdn.modrinth.com/data/<project>/versions/<version>/<file>
+fn modrinth_cdn_ids(url: &str) -> Option<(String, String)> {
+    let mut parts = url.strip_prefix("https://cdn.modrinth.com/data/")?.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(project_id), Some("versions"), Some(version_id)) => Some((project_id.to_string(), version_id.to_string())),
+        _ => None,
+    }
+}
+
+// The mirrors are tried in order until one serves a file matching the index's hash,
+// returns the position of the mirror that did
+fn download_modpack_file(entry: &ModpackFile, target: &Path, client: &Client) -> Result<usize, Box<dyn Error>> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    for (index, url) in entry.downloads.iter().enumerate() {
+        explain("GET", url);
+        let bytes = match send(prepare(client.get(url), url))
+            .and_then(|resp| resp.error_for_status())
+            .and_then(|resp| resp.bytes()) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                warn(&format!("Couldn't get {} from {}", entry.path, url));
+                continue;
+            }
+        };
+        if format!("{:x}", Sha512::digest(&bytes)) != entry.hashes.sha512 {
+            warn(&format!("{} from {} doesn't match the hash in the modpack", entry.path, url));
+            continue;
+        }
+        fs::write(target, &bytes).map_err(|e| save_error(target, e))?;
+        status(&format!("Downloaded {} from {}", entry.path, url));
+        return Ok(index);
+    }
+    Err(Box::new(RustdrynthError::Network(format!("None of the mirrors of {} could be downloaded", entry.path))))
+}
+
+// "overrides" is copied for every install, "client-overrides" on top of it
+fn extract_overrides(archive: &mut ZipArchive<fs::File>, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+    for prefix in ["overrides", "client-overrides"] {
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let Some(path) = entry.enclosed_name() else {
+                continue;
+            };
+            let relative = match path.strip_prefix(prefix) {
+                Ok(relative) if !relative.as_os_str().is_empty() => relative,
+                _ => continue,
+            };
+            let target = output_dir.join(relative);
+            if entry.is_dir() {
+                fs::create_dir_all(&target)?;
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::File::create(&target)
+                .and_then(|mut file| io::copy(&mut entry, &mut file))
+                .map_err(|e| save_error(&target, e))?;
+        }
+    }
+    Ok(())
+}
+
+fn get_project(project_id: &str, client: &Client) -> Result<ProjectResponse, Box<dyn Error>> {
+    let resp_txt = api_get(client, &format!("/project/{}", project_id), &[])?;
+    let processed_response: Result<ProjectResponse, _> = serde_json::from_str(&resp_txt);
+    let project = match processed_response {
+        Ok(prj) => prj,
+        Err(e) => {
+            return Err(Box::new(RustdrynthError::Parse(format!("Couldn't get the project \"{}\": {}{}", project_id, e, token_hint()))));
+        }
+    };
+    if project_id != project.id {
+        remember_project_id(project_id, &project.id);
+    }
+    Ok(project)
+}
+
+// The ids of the --exclude projects, an unknown one is only warned about since it can't match anything
+fn excluded_project_ids<'a>(exclude: impl IntoIterator<Item = &'a String>, client: &Client) -> HashSet<String> {
+    exclude.into_iter().filter_map(|project| match resolve_project_id(project, client) {
+        Ok(project_id) => Some(project_id),
+        Err(_) => {
+            warn(&format!("Warning: {} isn't a project on Modrinth, nothing is excluded for it", project));
+            None
+        }
+    }).collect()
+}
+
+// Only for when the id is all that's needed, a slug seen before skips the request
+fn resolve_project_id(project: &str, client: &Client) -> Result<String, Box<dyn Error>> {
+    match cached_project_id(project) {
+        Some(id) => Ok(id),
+        None => Ok(get_project(project, client)?.id),
+    }
+}
+
+// Slugs are only unique within an API, so the ids are kept per API url
+fn cached_project_id(project: &str) -> Option<String> {
+    let id = slug_cache().lock().unwrap().get(&slug_cache_key(project)).map(|cached| cached.id.clone())?;
+    verbose(&format!("Using the cached id {} for {}", id, project));
+    Some(id)
+}
+
+fn slug_cache_key(slug: &str) -> String {
+    format!("{} {}", api_url(), slug)
+}
+
+fn slug_cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|cache_dir| cache_dir.join("rustdrynth").join("slugs.json"))
+}
+
+// A project's id never changes but its slug can be renamed and taken by another project,
+// so the mappings expire. Versions are never stored here, they change all the time
+fn slug_cache() -> &'static Mutex<HashMap<String, CachedId>> {
+    static SLUGS: OnceLock<Mutex<HashMap<String, CachedId>>> = OnceLock::new();
+    SLUGS.get_or_init(|| {
+        let mut slugs: HashMap<String, CachedId> = slug_cache_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        let now = Utc::now().timestamp();
+        slugs.retain(|_, cached| now - cached.saved < SLUG_CACHE_TTL);
+        Mutex::new(slugs)
+    })
+}
+
+// Failing to save only costs a request next time, so errors are ignored
+fn remember_project_id(slug: &str, id: &str) {
+    let key = slug_cache_key(slug);
+    let mut slugs = slug_cache().lock().unwrap();
+    if slugs.get(&key).is_some_and(|cached| cached.id == id) {
+        return;
+    }
+    slugs.insert(key, CachedId { id: id.to_string(), saved: Utc::now().timestamp() });
+    if let (Some(path), Ok(contents)) = (slug_cache_path(), serde_json::to_string(&*slugs)) {
+        let _ = path.parent().map(fs::create_dir_all);
+        let _ = fs::write(path, contents);
+    }
+}
+
+fn plan_cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|cache_dir| cache_dir.join("rustdrynth").join("plans.json"))
+}
+
+fn plan_cache() -> &'static Mutex<HashMap<String, DependencyPlan>> {
+    static PLANS: OnceLock<Mutex<HashMap<String, DependencyPlan>>> = OnceLock::new();
+    PLANS.get_or_init(|| {
+        let mut plans: HashMap<String, DependencyPlan> = plan_cache_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        let now = Utc::now().timestamp();
+        plans.retain(|_, plan| now - plan.saved < DEPENDENCY_PLAN_TTL);
+        Mutex::new(plans)
+    })
+}
+
+// Like the slug cache, failing to save only costs the requests next time
+fn remember_plan(key: &str, plan: &DependencyPlan) {
+    let mut plans = plan_cache().lock().unwrap();
+    plans.insert(key.to_string(), plan.clone());
+    if let (Some(path), Ok(contents)) = (plan_cache_path(), serde_json::to_string(&*plans)) {
+        let _ = path.parent().map(fs::create_dir_all);
+        let _ = fs::write(path, contents);
+    }
+}
+
+fn clear_cache() -> Result<(), Box<dyn Error>> {
+    let mut removed = false;
+    for path in [slug_cache_path(), plan_cache_path()].into_iter().flatten().filter(|path| path.exists()) {
+        fs::remove_file(&path).map_err(|e| save_error(&path, e))?;
+        println!("Removed {}", path.display());
+        removed = true;
+    }
+    if !removed {
+        println!("The cache is already empty");
+    }
+    Ok(())
+}
+
+// Fetches every project with a single request, falling back to one request per project
+// if the bulk endpoint fails. The projects are keyed by id
+fn get_projects(project_ids: &[String], client: &Client) -> Result<HashMap<String, ProjectResponse>, Box<dyn Error>> {
+    if project_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let bulk = api_get(client, "/projects", &[("ids", &serde_json::to_string(project_ids)?)])
+        .and_then(|resp_txt| Ok(serde_json::from_str::<Vec<ProjectResponse>>(&resp_txt)?));
+    let projects = match bulk {
+        Ok(projects) => projects,
+        Err(e) => {
+            verbose(&format!("Couldn't get the projects at once, getting them one by one: {}", e));
+            project_ids.iter().map(|project_id| get_project(project_id, client)).collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    Ok(projects.into_iter().map(|project| (project.id.clone(), project)).collect())
+}
+
+fn list_game_versions(allow_snapshots: bool, client: &Client) -> Result<(), Box<dyn Error>> {
+    for tag in get_game_versions(allow_snapshots, client)? {
+        println!("{} ({})", tag.version, tag.version_type);
+    }
+    Ok(())
+}
+
+// Trims the version and warns before any request is made if Modrinth doesn't know it,
+// an unknown version would otherwise just return empty results
+fn normalize_game_version(game_version: &str, check: bool, client: &Client) -> String {
+    let game_version = expand_game_version(game_version, client);
+    if !check || game_version.is_empty() || game_version == ANY_GAME_VERSION {
+        return game_version;
+    }
+    if let Ok(tags) = get_all_game_versions(client) {
+        if !tags.iter().any(|tag| tag.version == game_version) {
+            warn(&format!("Warning: \"{}\" isn't a Minecraft version known to Modrinth, you may not get any results", game_version));
+        }
+    }
+    game_version
+}
+
+// Expands the range into every release between both ends, a missing end leaves that side open
+fn game_version_range(min_version: Option<&str>, max_version: Option<&str>, client: &Client) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut releases: Vec<String> = get_game_versions(false, client)?.into_iter().map(|tag| tag.version).collect();
+    releases.reverse();
+    let position = |version: &str| match releases.iter().position(|release| *release == expand_game_version(version, client)) {
+        Some(position) => Ok(position),
+        None => Err(Box::new(RustdrynthError::InvalidInput(format!("\"{}\" isn't a Minecraft release known to Modrinth", version)))),
+    };
+    let min = match min_version {
+        Some(min_version) => position(min_version)?,
+        None => 0,
+    };
+    let max = match max_version {
+        Some(max_version) => position(max_version)?,
+        None => releases.len().saturating_sub(1),
+    };
+    if min > max {
+        return Err(Box::new(RustdrynthError::InvalidInput("The minimum version is newer than the maximum version".to_string())));
+    }
+    Ok(releases[min..=max].to_vec())
+}
+
+// User aliases are expanded first, so they can also point at "latest"
+fn expand_game_version(game_version: &str, client: &Client) -> String {
+    let game_version = expand_alias(game_version);
+    if game_version != "latest" {
+        return game_version;
+    }
+    match get_game_versions(false, client) {
+        Ok(releases) if !releases.is_empty() => releases[0].version.clone(),
+        _ => {
+            warn("Couldn't get the latest Minecraft release, using \"latest\" as is");
+            game_version
+        }
+    }
+}
+
+fn expand_alias(value: &str) -> String {
+    let value = value.trim();
+    match config().aliases.get(value) {
+        Some(expanded) => {
+            verbose(&format!("Expanded the alias {} to {}", value, expanded));
+            expanded.clone()
+        }
+        None => value.to_string(),
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn load_config(path: &Path) -> Result<(), Box<dyn Error>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(Box::new(RustdrynthError::Io(format!("Couldn't read the config file {}: {}", path.display(), e)))),
+    };
+    let loaded: Config = match toml::from_str(&contents) {
+        Ok(loaded) => loaded,
+        Err(e) => return Err(Box::new(RustdrynthError::Parse(format!("Couldn't read the config file {}: {}", path.display(), e)))),
+    };
+    let _ = CONFIG.set(loaded);
+    Ok(())
+}
+
+// Read once from the rustdrynth folder of the config dir unless --config gave one, a missing
+// file is an empty config
+fn config() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        let Some(path) = dirs::config_dir().map(|config_dir| config_dir.join("rustdrynth").join("config.toml")) else {
+            return Config::default();
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Config::default();
+        };
+        toml::from_str(&contents).unwrap_or_else(|e| {
+            warn(&format!("Couldn't read {}, ignoring it: {}", path.display(), e));
+            Config::default()
+        })
+    })
+}
+
+// Versions come newest first, the way the API returns them
+fn get_all_game_versions(client: &Client) -> Result<Vec<GameVersionTag>, Box<dyn Error>> {
+    let resp_txt = api_get(client, "/tag/game_version", &[])?;
+    Ok(serde_json::from_str(&resp_txt)?)
+}
+
+// Only warns like an unknown game version does, the search still runs. Loaders are
+// accepted too since they're passed as categories
+fn check_categories(categories: &[String], client: &Client) {
+    if categories.is_empty() {
+        return;
+    }
+    let known: Vec<String> = ["/tag/category", "/tag/loader"].iter()
+        .filter_map(|path| api_get(client, path, &[]).ok())
+        .filter_map(|resp_txt| serde_json::from_str::<Vec<CategoryTag>>(&resp_txt).ok())
+        .flatten().map(|tag| tag.name).collect();
+    if known.is_empty() {
+        return;
+    }
+    for category in categories.iter().filter(|category| !known.contains(category)) {
+        let closest = known.iter().map(|name| (levenshtein(name, category), name)).min();
+        match closest {
+            // Anything further away is more likely a different word than a typo
+            Some((distance, name)) if distance <= 3 => warn(&format!("Warning: unknown category '{}', did you mean '{}'?", category, name)),
+            _ => warn(&format!("Warning: unknown category '{}', you may not get any results", category)),
+        }
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn get_game_versions(allow_snapshots: bool, client: &Client) -> Result<Vec<GameVersionTag>, Box<dyn Error>> {
+    Ok(filter_game_versions(get_all_game_versions(client)?, allow_snapshots))
+}
+
+// Betas and alphas are never listed, snapshots only when asked for
+fn filter_game_versions(tags: Vec<GameVersionTag>, allow_snapshots: bool) -> Vec<GameVersionTag> {
+    tags.into_iter()
+        .filter(|tag| tag.version_type == "release" || (allow_snapshots && tag.version_type == "snapshot"))
+        .collect()
+}
+
+fn check_for_update(client: &Client) -> Result<(), Box<dyn Error>> {
+    let resp_txt = cached_get("https://api.github.com/repos/Tomyatana/Rustdrynth/releases/latest", client)?;
+    let release: GithubRelease = match serde_json::from_str(&resp_txt) {
+        Ok(release) => release,
+        Err(e) => {
+            return Err(Box::new(RustdrynthError::Parse(format!("Couldn't get the latest release: {}", e))));
+        }
+    };
+    let current = env!("CARGO_PKG_VERSION");
+    if parse_version(&release.tag_name) > parse_version(current) {
+        println!("Rustdrynth {} is available (you have {}), get it from {}", release.tag_name, current, release.html_url);
+    } else {
+        println!("Rustdrynth {} is up to date", current);
+    }
+    Ok(())
+}
+
+// "v1.2.3" -> [1, 2, 3], anything that isn't a number is ignored
+fn parse_version(version: &str) -> Vec<u64> {
+    version.trim_start_matches('v').split('.').filter_map(|part| part.parse().ok()).collect()
+}
+
+// The flag, then the config file, then a backups folder in the data dir
+fn resolve_backup_dir(backup_dir: Option<&Path>) -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(backup_dir) = backup_dir.or(config().backup_dir.as_deref()) {
+        return Ok(backup_dir.to_path_buf());
+    }
+    match dirs::data_dir() {
+        Some(data_dir) => Ok(data_dir.join("rustdrynth").join("backups")),
+        None => Err(Box::new(RustdrynthError::NotFound("Couldn't find the data dir to keep the backups in, pass --backup-dir".to_string()))),
+    }
+}
+
+// Every file of the folder and its subfolders, relative to it
+fn collect_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(dir, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn backup_mods(mods_dir: &str, backup_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let mods_dir = Path::new(mods_dir);
+    let mut files = Vec::new();
+    collect_files(mods_dir, Path::new(""), &mut files)?;
+    files.sort();
+    fs::create_dir_all(backup_dir)?;
+    let archive_path = backup_dir.join(format!("mods-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    let mut archive = zip::ZipWriter::new(fs::File::create(&archive_path).map_err(|e| save_error(&archive_path, e))?);
+    for file in files.iter() {
+        // Zip entries always use forward slashes
+        let name = file.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        archive.start_file(name, zip::write::SimpleFileOptions::default())?;
+        io::copy(&mut fs::File::open(mods_dir.join(file))?, &mut archive)?;
+    }
+    archive.finish()?;
+    println!("Backed up {} files of {} to {}", files.len(), mods_dir.display(), archive_path.display());
+    Ok(())
+}
+
+// The mods folder ends up like it was backed up, jars that were added since are removed
+fn restore_mods(backup: &Path, mods_dir: &str, yes: bool) -> Result<(), Box<dyn Error>> {
+    let mut archive = match fs::File::open(backup) {
+        Ok(file) => ZipArchive::new(file)?,
+        Err(e) => return Err(Box::new(RustdrynthError::NotFound(format!("Couldn't open the backup {}: {}", backup.display(), e)))),
+    };
+    let mods_dir = Path::new(mods_dir);
+    let mut names = HashSet::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if let Some(path) = entry.enclosed_name().filter(|_| !entry.is_dir()) {
+            names.insert(path);
+        }
+    }
+    let added: Vec<PathBuf> = jar_hashes(&mods_dir.display().to_string())?.into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| path.strip_prefix(mods_dir).is_ok_and(|relative| !names.contains(relative)))
+        .collect();
+    // --quiet only skips the prompt when nothing gets removed, deleting mods always needs --yes or an answer
+    let ask = !yes && (!settings().quiet || !added.is_empty());
+    if ask && !confirm(&format!("Restore {} files from {} into {}, removing {} jars that aren't in it?", names.len(), backup.display(), mods_dir.display(), added.len()))? {
+        println!("Nothing was restored");
+        return Ok(());
+    }
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        let target = mods_dir.join(path);
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::File::create(&target)
+            .and_then(|mut file| io::copy(&mut entry, &mut file))
+            .map_err(|e| save_error(&target, e))?;
+    }
+    for path in added.iter() {
+        fs::remove_file(path)?;
+        status(&format!("Removed {}, it isn't in the backup", path.display()));
+    }
+    println!("Restored {} files from {} into {}", names.len(), backup.display(), mods_dir.display());
+    Ok(())
+}
+
+// Commands working on installed mods use the current directory unless --mcdir or
+// --minecraft-dir is given
+fn mods_dir(mcdir: bool, minecraft_path: Option<&Path>) -> String {
+    if let Some(minecraft_path) = minecraft_path {
+        minecraft_path.join("mods").display().to_string()
+    } else if mcdir && !check_for_mods_dir().is_empty() {
+        check_for_mods_dir()
+    } else {
+        ".".to_string()
+    }
+}
+
+// A jar whose hash Modrinth doesn't know was either changed after the download or never came
+// from Modrinth, one that isn't even a readable zip is surely corrupted
+fn verify_mods(mods_dir: &str, client: &Client) -> Result<(), Box<dyn Error>> {
+    let jars = jar_hashes(mods_dir)?;
+    let hashes: Vec<&String> = jars.iter().map(|(_, hash)| hash).collect();
+    let known = if hashes.is_empty() { HashMap::new() } else { get_versions_from_hashes(&hashes, client)? };
+    let entries: Vec<serde_json::Value> = jars.iter().map(|(path, hash)| {
+        let version = known.get(hash);
+        let status = match version {
+            Some(_) => "ok",
+            None if fs::File::open(path).ok().and_then(|file| ZipArchive::new(file).ok()).is_none() => "corrupted",
+            None => "unknown",
+        };
+        serde_json::json!({
+            "file": path.display().to_string(),
+            "sha512": hash,
+            "status": status,
+            "project_id": version.map(|version| &version.project_id),
+            "version_number": version.map(|version| &version.version_number),
+        })
+    }).collect();
+    let failed = entries.iter().filter(|entry| entry["status"] != "ok").count();
+    if settings().format == OutputFormat::Json {
+        print_json(&entries)?;
+    } else {
+        for entry in entries.iter().filter(|entry| entry["status"] != "ok") {
+            let file = entry["file"].as_str().unwrap_or_default();
+            match entry["status"].as_str() {
+                Some("corrupted") => println!("{} is corrupted, it isn't a readable jar", file),
+                _ => println!("{} doesn't match any file on Modrinth, it was modified or isn't from Modrinth", file),
+            }
+        }
+        println!("{} of the {} jars in {} match a file on Modrinth", entries.len() - failed, entries.len(), mods_dir);
+    }
+    if failed > 0 {
+        return Err(Box::new(RustdrynthError::Conflict(format!("{} jars couldn't be verified", failed))));
+    }
+    Ok(())
+}
+
+fn list_mods(mods_dir: &str, client: &Client) -> Result<(), Box<dyn Error>> {
+    let jars = identify_jars(mods_dir, client)?;
+    if structured_output() {
+        let entries: Vec<serde_json::Value> = jars.iter().map(|(path, version)| serde_json::json!({
+            "file": path.display().to_string(),
+            "project_id": version.as_ref().map(|version| &version.project_id),
+            "version_number": version.as_ref().map(|version| &version.version_number),
+            "metadata": if version.is_none() { read_jar_metadata(path) } else { None },
+        })).collect();
+        return print_structured("mods", &entries);
+    }
+    if jars.is_empty() {
+        println!("No jars found in {}", mods_dir);
+    }
+    let project_ids: Vec<String> = jars.iter().flat_map(|(_, version)| version.iter().map(|version| version.project_id.clone())).collect();
+    let projects = get_projects(&project_ids, client)?;
+    for (path, version) in jars.iter() {
+        match version {
+            Some(version) => {
+                let project = match projects.get(&version.project_id) {
+                    Some(project) => project.clone(),
+                    None => get_project(&version.project_id, client)?,
+                };
+                println!("{}", terminal_text(&format!("\"{}\" {} - {} ({})", project.title, version.version_number, project_handle(&project.slug, &project.id), path.display())));
+            },
+            None => match read_jar_metadata(path) {
+                Some(metadata) => {
+                    let version = metadata.version.map(|version| format!(" {}", version)).unwrap_or_default();
+                    let game_versions = metadata.game_versions.map(|range| format!(", Minecraft {}", range)).unwrap_or_default();
+                    println!("{}", terminal_text(&format!("{} isn't a Modrinth file, its metadata says {}{} for {}{}", path.display(), metadata.mod_id, version, metadata.loader, game_versions)));
+                }
+                None => println!("{} isn't a Modrinth file", path.display()),
+            },
+        }
+    }
+    Ok(())
+}
+
+// For the jars Modrinth doesn't know, only the first mod of a jar is described. Archives
+// that can't be opened or parsed are left unidentified
+fn read_jar_metadata(path: &Path) -> Option<JarMetadata> {
+    let mut archive = ZipArchive::new(fs::File::open(path).ok()?).ok()?;
+    if let Some(contents) = read_archive_entry(&mut archive, "fabric.mod.json") {
+        let manifest: serde_json::Value = serde_json::from_str(&contents).ok()?;
+        let game_versions = match &manifest["depends"]["minecraft"] {
+            serde_json::Value::String(range) => Some(range.clone()),
+            serde_json::Value::Array(ranges) => Some(ranges.iter().filter_map(|range| range.as_str()).collect::<Vec<_>>().join(" || ")),
+            _ => None,
+        };
+        return Some(JarMetadata {
+            mod_id: manifest["id"].as_str()?.to_string(),
+            version: manifest["version"].as_str().map(String::from),
+            loader: "fabric",
+            game_versions,
+        });
+    }
+    for (name, loader) in [("META-INF/neoforge.mods.toml", "neoforge"), ("META-INF/mods.toml", "forge")] {
+        let Some(contents) = read_archive_entry(&mut archive, name) else {
+            continue;
+        };
+        let manifest: toml::Table = toml::from_str(&contents).ok()?;
+        let first = manifest.get("mods")?.as_array()?.first()?.as_table()?;
+        let mod_id = first.get("modId")?.as_str()?.to_string();
+        let game_versions = manifest.get("dependencies")
+            .and_then(|dependencies| dependencies.get(&mod_id))
+            .and_then(|dependencies| dependencies.as_array())
+            .and_then(|dependencies| dependencies.iter().find(|dependency| dependency.get("modId").and_then(|id| id.as_str()) == Some("minecraft")))
+            .and_then(|dependency| dependency.get("versionRange"))
+            .and_then(|range| range.as_str())
+            .map(String::from);
+        return Some(JarMetadata {
+            mod_id,
+            // Usually a ${file.jarVersion} placeholder filled in from the jar's manifest
+            version: first.get("version").and_then(|version| version.as_str()).filter(|version| !version.starts_with("${")).map(String::from),
+            loader,
+            game_versions,
+        });
+    }
+    None
+}
+
+fn read_archive_entry(archive: &mut ZipArchive<fs::File>, name: &str) -> Option<String> {
+    let mut contents = String::new();
+    archive.by_name(name).ok()?.read_to_string(&mut contents).ok()?;
+    Some(contents)
+}
+
+fn remove_mod(project: &str, mods_dir: &str, force: bool, client: &Client) -> Result<(), Box<dyn Error>> {
+    let target = get_project(project, client)?;
+    let installed = installed_mods(mods_dir, client)?;
+
+    let target_path = match installed.iter().find(|(_, version)| version.project_id == target.id) {
+        Some((path, _)) => path,
+        None => {
+            return Err(Box::new(RustdrynthError::NotFound(format!("Couldn't find {} in {}", target.title, mods_dir))));
+        }
+    };
+
+    let dependents: Vec<&(PathBuf, ProjectVersion)> = installed.iter()
+        .filter(|(_, version)| version.project_id != target.id)
+        .filter(|(_, version)| version.dependencies.iter()
+            .any(|dependency| dependency.project_id.as_ref() == Some(&target.id) && dependency.dependency_type == "required"))
+        .collect();
+    if !dependents.is_empty() {
+        println!("{}", terminal_text(&format!("The following installed mods require {}:", target.title)));
+        for (path, version) in dependents.iter() {
+            let dependent = get_project(&version.project_id, client)?;
+            println!("{}", terminal_text(&format!("\"{}\" - {} ({})", dependent.title, project_handle(&dependent.slug, &dependent.id), path.display())));
+        }
+        if !force {
+            return Err(Box::new(RustdrynthError::Conflict(format!("{} is required by other installed mods, use --force to remove it anyway", target.title))));
+        }
+    }
+
+    fs::remove_file(target_path)?;
+    println!("Removed {}", target_path.display());
+    Ok(())
+}
+
+// Jars that can't be matched to a Modrinth version are skipped
+// A folder that doesn't exist yet has nothing installed
+fn installed_project_ids(mods_dir: &str, client: &Client) -> Result<HashSet<String>, Box<dyn Error>> {
+    if !Path::new(mods_dir).is_dir() {
+        return Ok(HashSet::new());
+    }
+    Ok(installed_mods(mods_dir, client)?.into_iter().map(|(_, version)| version.project_id).collect())
+}
+
+fn installed_mods(mods_dir: &str, client: &Client) -> Result<Vec<(PathBuf, ProjectVersion)>, Box<dyn Error>> {
+    Ok(identify_jars(mods_dir, client)?.into_iter()
+        .filter_map(|(path, version)| version.map(|version| (path, version)))
+        .collect())
+}
+
+// A jar along with its Modrinth version, if it's a Modrinth file at all
+type IdentifiedJar = (PathBuf, Option<ProjectVersion>);
+
+// Every jar of the folder is resolved with a single bulk request
+fn identify_jars(mods_dir: &str, client: &Client) -> Result<Vec<IdentifiedJar>, Box<dyn Error>> {
+    let jars = jar_hashes(mods_dir)?;
+    if jars.is_empty() {
+        return Ok(Vec::new());
+    }
+    let hashes: Vec<&String> = jars.iter().map(|(_, hash)| hash).collect();
+    let mut versions = get_versions_from_hashes(&hashes, client)?;
+    Ok(jars.into_iter().map(|(path, hash)| {
+        let version = versions.remove(&hash);
+        (path, version)
+    }).collect())
+}
+
+// Every jar of the folder with its sha512 hash, sorted by path
+fn jar_hashes(mods_dir: &str) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+    let mut jars = Vec::new();
+    for entry in fs::read_dir(mods_dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "jar") {
+            continue;
+        }
+        let hash = hash_file(&path)?;
+        jars.push((path, hash));
+    }
+    jars.sort();
+    Ok(jars)
+}
+
+// A jar, its hash and the newest version of its mod, which is the installed one if it's up to date
+type UpdateCandidate = (PathBuf, String, GameVersion);
+
+// Every installed jar with a version for the loader and game version
+fn newest_versions(mods_dir: &str, loader: &str, game_version: &str, client: &Client) -> Result<Vec<UpdateCandidate>, Box<dyn Error>> {
+    let jars = jar_hashes(mods_dir)?;
+    if jars.is_empty() {
+        return Ok(Vec::new());
+    }
+    let hashes: Vec<&String> = jars.iter().map(|(_, hash)| hash).collect();
+    let mut updates = get_updates_from_hashes(&hashes, loader, game_version, client)?;
+    Ok(jars.into_iter().filter_map(|(path, hash)| match updates.remove(&hash) {
+        Some(update) => Some((path, hash, update)),
+        None => {
+            verbose(&format!("{} has no {} {} version on Modrinth", path.display(), loader, game_version));
+            None
+        }
+    }).collect())
+}
+
+fn is_up_to_date(hash: &str, newest: &GameVersion) -> bool {
+    newest.files.iter().any(|file| file.hashes.get("sha512").is_some_and(|file_hash| file_hash == hash))
+}
+
+// A jar kept by an earlier run with the same name isn't overwritten, the new one gets a
+// numbered name next to it, e.g. "sodium-1.jar"
+fn free_old_path(old_dir: &Path, path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default();
+    let target = old_dir.join(file_name);
+    if !target.exists() {
+        return target;
+    }
+    let stem = Path::new(file_name).file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = Path::new(file_name).extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    (1..).map(|number| old_dir.join(format!("{}-{}{}", stem, number, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(target)
+}
+
+fn update_mods(mods_dir: &str, loader: &str, game_version: &str, keep_old: bool, yes: bool, client: &Client) -> Result<(), Box<dyn Error>> {
+    let updates = newest_versions(mods_dir, loader, game_version, client)?;
+    let project_ids: Vec<String> = updates.iter().map(|(_, _, update)| update.project_id.clone()).collect();
+    let projects = get_projects(&project_ids, client)?;
+    let slug = |project_id: &String| projects.get(project_id).map_or(project_id.clone(), |project| project.slug.clone());
+    let old_dir = Path::new(mods_dir).join(".old");
+    let mut results = Vec::new();
+    let (current, pending): (Vec<UpdateCandidate>, Vec<UpdateCandidate>) = updates.into_iter().partition(|(_, hash, update)| is_up_to_date(hash, update));
+    for (_, _, update) in current {
+        results.push(BatchResult { project: slug(&update.project_id), project_id: update.project_id, action: "skipped", version: update.version_number, error: None });
+    }
+    let files: Vec<(String, u64)> = pending.iter()
+        .filter_map(|(_, _, update)| update.files.first().map(|file| (format!("{} {}", file.filename, update.version_number), file.size)))
+        .collect();
+    let removed: Vec<String> = if keep_old { Vec::new() } else { pending.iter().map(|(path, _, _)| path.display().to_string()).collect() };
+    if !pending.is_empty() && !confirm_files(&files, &removed, yes, false)? {
+        return Ok(());
+    }
+    for (path, _, update) in pending {
+        let project = slug(&update.project_id);
+        // The old jar is moved aside first, so a new file with the same name can't overwrite
+        // it and it can be put back if the download fails
+        fs::create_dir_all(&old_dir)?;
+        let old_path = free_old_path(&old_dir, &path);
+        fs::rename(&path, &old_path)?;
+        match download_jar(update.files, true, client, Path::new(mods_dir)) {
+            Ok(_) => {
+                if !keep_old {
+                    fs::remove_file(&old_path)?;
+                }
+                results.push(BatchResult { project, project_id: update.project_id, action: "updated", version: update.version_number, error: None });
+            }
+            Err(e) => {
+                fs::rename(&old_path, &path)?;
+                results.push(BatchResult { project, project_id: update.project_id, action: "failed", version: update.version_number, error: Some(e.to_string()) });
+            }
+        }
+    }
+    if !keep_old {
+        // Only succeeds if it's empty, so jars kept by an earlier --keep-old stay
+        let _ = fs::remove_dir(&old_dir);
+    }
+    if results.is_empty() {
+        println!("None of the jars in {} have a {} {} version on Modrinth", mods_dir, loader, game_version);
+        return Ok(());
+    }
+    print_results(&results)?;
+    batch_outcome(&results)
+}
+
+// Jars that aren't on Modrinth can't be checked, so they're always kept
+fn prune_mods(mods_dir: &str, loader: &str, game_version: &str, dry_run: bool, keep_old: bool, client: &Client) -> Result<(), Box<dyn Error>> {
+    let jars = jar_hashes(mods_dir)?;
+    if jars.is_empty() {
+        println!("There are no jars in {}", mods_dir);
+        return Ok(());
+    }
+    let hashes: Vec<&String> = jars.iter().map(|(_, hash)| hash).collect();
+    let known = get_versions_from_hashes(&hashes, client)?;
+    let updates = get_updates_from_hashes(&hashes, loader, game_version, client)?;
+    let project_ids: Vec<String> = known.values().map(|version| version.project_id.clone()).collect();
+    let projects = get_projects(&project_ids, client)?;
+    let old_dir = Path::new(mods_dir).join(".old");
+    let mut pruned = Vec::new();
+    for (path, hash) in jars.iter() {
+        let Some(version) = known.get(hash) else {
+            verbose(&format!("{} isn't on Modrinth, it's kept", path.display()));
+            continue;
+        };
+        if updates.contains_key(hash) {
+            continue;
+        }
+        let project = projects.get(&version.project_id).map_or(version.project_id.clone(), |project| project_handle(&project.slug, &project.id));
+        let action = if dry_run {
+            "would prune"
+        } else if keep_old {
+            fs::create_dir_all(&old_dir)?;
+            fs::rename(path, free_old_path(&old_dir, path))?;
+            "moved to .old"
+        } else {
+            fs::remove_file(path)?;
+            "removed"
+        };
+        pruned.push((path.display().to_string(), project, action, format!("no {} {} version", loader, game_version)));
+    }
+    if settings().format == OutputFormat::Json {
+        let entries: Vec<serde_json::Value> = pruned.iter().map(|(file, project, action, reason)| serde_json::json!({
+            "file": file,
+            "project": project,
+            "action": action,
+            "reason": reason,
+        })).collect();
+        return print_json(&entries);
+    }
+    if pruned.is_empty() {
+        println!("Every jar of {} that's on Modrinth has a {} {} version", mods_dir, loader, game_version);
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = pruned.into_iter().map(|(file, project, action, reason)| vec![file, project, action.to_string(), reason]).collect();
+    print_table(&["File", "Project", "Action", "Reason"], &rows);
+    if dry_run {
+        println!("Dry run, nothing was removed");
+    }
+    Ok(())
+}
+
+// Each check is a single bulk request, so even short intervals stay far from the rate limit
+fn watch_mods(mods_dir: &str, loader: &str, game_version: &str, interval: u64, auto_update: bool, once: bool, client: &Client) -> Result<(), Box<dyn Error>> {
+    let mut notified = HashSet::new();
+    loop {
+        match newest_versions(mods_dir, loader, game_version, client) {
+            Ok(updates) => {
+                let mut available = false;
+                for (path, hash, update) in updates.iter().filter(|(_, hash, update)| !is_up_to_date(hash, update)) {
+                    available = true;
+                    if notified.insert(hash.clone()) {
+                        println!("{} can be updated to {}", path.display(), update.version_number);
+                    }
+                }
+                if available && auto_update {
+                    update_mods(mods_dir, loader, game_version, false, true, client)?;
+                }
+            }
+            // A long running watch shouldn't die because of a single failed check
+            Err(e) if !once => warn(&format!("Couldn't check for updates: {}", e)),
+            Err(e) => return Err(e),
+        }
+        if once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(interval * 60));
+    }
+}
+
+// With "any" game version the filter is left out, like for the version lists
+fn get_updates_from_hashes(hashes: &[&String], loader: &str, game_version: &str, client: &Client) -> Result<HashMap<String, GameVersion>, Box<dyn Error>> {
+    let mut body = serde_json::json!({ "hashes": hashes, "algorithm": "sha512", "loaders": [loader] });
+    if game_version != ANY_GAME_VERSION {
+        body["game_versions"] = serde_json::json!([game_version]);
+    }
+    let resp_txt = api_post(client, "/version_files/update", &body)?;
+    Ok(serde_json::from_str(&resp_txt)?)
+}
+
+fn get_versions_from_hashes(hashes: &[&String], client: &Client) -> Result<HashMap<String, ProjectVersion>, Box<dyn Error>> {
+    let resp_txt = api_post(client, "/version_files", &serde_json::json!({ "hashes": hashes, "algorithm": "sha512" }))?;
+    Ok(serde_json::from_str(&resp_txt)?)
+}
+
+fn identify_file(file: &Path, client: &Client) -> Result<(), Box<dyn Error>> {
+    let version = match get_version_from_hash(&hash_file(file)?, client) {
+        Ok(version) => version,
+        Err(e) if is_not_found(&*e) => return Err(Box::new(RustdrynthError::NotFound(format!("{} doesn't match any file on Modrinth", file.display())))),
+        Err(e) => return Err(e),
+    };
+    let project = get_project(&version.project_id, client)?;
+    println!("{}", terminal_text(&format!("{} - {}", project_type_label(&project.project_type), project.title)));
+    println!("Project: {}", project_handle(&project.slug, &project.id));
+    println!("Version: {} ({})", version.name, version.version_number);
+    Ok(())
+}
+
+fn get_version_from_hash(hash: &str, client: &Client) -> Result<ProjectVersion, Box<dyn Error>> {
+    let resp_txt = api_get(client, &format!("/version_file/{}", hash), &[("algorithm", "sha512")])?;
+    Ok(serde_json::from_str(&resp_txt)?)
+}
+
+fn hash_file(path: &Path) -> Result<String, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    Ok(format!("{:x}", Sha512::digest(&bytes)))
+}
+
+// Responses are memoized by url for the duration of a single run, so resolving
+// the same project or version list more than once only hits the API once
+fn cached_get(url: &str, client: &Client) -> Result<String, Box<dyn Error>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(cached) = cache.lock().unwrap().get(url) {
+        return Ok(cached.clone());
+    }
+    explain("GET", url);
+    let resp = send(prepare(client.get(url), url))?;
+    if resp.status() == StatusCode::UNAUTHORIZED || resp.status() == StatusCode::FORBIDDEN {
+        return Err(Box::new(RustdrynthError::Unauthorized(match settings().token {
+            Some(_) => format!("The token doesn't give access to {}", url),
+            None => format!("{} needs authentication, pass your Modrinth token with --token or MODRINTH_TOKEN", url),
+        })));
+    }
+    // Neither is cached, the next request for the url asks again
+    if resp.status() == StatusCode::NOT_FOUND {
+        let hint = if url.starts_with(api_url()) || url.starts_with(&api_v3_url()) { token_hint() } else { "" };
+        return Err(Box::new(RustdrynthError::NotFound(format!("Nothing was found at {}{}", url, hint))));
+    }
+    if resp.status().is_server_error() || resp.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(Box::new(RustdrynthError::Network(format!("{} answered {}", url, resp.status()))));
+    }
+    let resp_txt = resp.text()?;
+    cache.lock().unwrap().insert(url.to_string(), resp_txt.clone());
+    Ok(resp_txt)
+}
+
+// Connection errors, timeouts and 5xx or 429 responses are retried up to --retries
+// times, waiting --retry-delay seconds in between, anything else is returned as is
+fn send(request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    let mut attempt = 0;
+    loop {
+        let Some(retry) = request.try_clone() else {
+            return request.send();
+        };
+        let result = retry.send();
+        let retryable = match &result {
+            Ok(resp) => resp.status().is_server_error() || resp.status() == StatusCode::TOO_MANY_REQUESTS,
+            Err(e) => e.is_connect() || e.is_timeout(),
+        };
+        if !retryable || attempt >= settings().retries {
+            if let (true, Ok(resp)) = (settings().show_ratelimit, &result) {
+                if let Some(ratelimit) = ratelimit(resp) {
+                    eprintln!("{}", ratelimit);
+                }
+            }
+            return result;
+        }
+        attempt += 1;
+        let delay = backoff(attempt);
+        status(&format!("Request failed, retrying in {:.1}s ({}/{})", delay.as_secs_f64(), attempt, settings().retries));
+        thread::sleep(delay);
+    }
+}
+
+// --retry-delay doubles with every attempt, plus up to half of it at random so the
+// downloads of a batch that failed together don't all retry at the same moment
+fn backoff(attempt: u32) -> Duration {
+    let base = settings().retry_delay.saturating_mul(1000).saturating_mul(1 << (attempt - 1).min(6));
+    let jitter = rand::rng().random_range(0..=base / 2);
+    Duration::from_millis(base + jitter)
+}
+
+// Only Modrinth's responses carry the headers
+fn ratelimit(resp: &Response) -> Option<String> {
+    let header = |name: &str| resp.headers().get(name).and_then(|value| value.to_str().ok());
+    let remaining = header("X-Ratelimit-Remaining")?;
+    let limit = header("X-Ratelimit-Limit")?;
+    match header("X-Ratelimit-Reset") {
+        Some(reset) => Some(format!("{} of {} requests left, resets in {}s", remaining, limit, reset)),
+        None => Some(format!("{} of {} requests left", remaining, limit)),
+    }
+}
+
+// Skips the cache on purpose, a cached response wouldn't tell anything about the current quota
+fn show_ratelimit(client: &Client) -> Result<(), Box<dyn Error>> {
+    let url = api_endpoint("/tag/project_type", &[])?;
+    explain("GET", &url);
+    let resp = send(prepare(client.get(&url), &url))?;
+    match ratelimit(&resp) {
+        Some(ratelimit) => println!("{}", ratelimit),
+        None => return Err(Box::new(RustdrynthError::Network("The API didn't send any rate limit headers".to_string()))),
+    }
+    Ok(())
+}
+
+// Unlisted and draft projects look like missing ones to anyone but their authors
+fn token_hint() -> &'static str {
+    match settings().token {
+        Some(_) => "",
+        None => ", if it's an unlisted or draft project of yours pass your token with --token",
+    }
+}
+
+fn api_url() -> &'static str {
+    settings().api_url.trim_end_matches('/')
+}
+
+// Collections are only in the v3 API, which sits next to the v2 one
+fn api_v3_url() -> String {
+    match api_url().strip_suffix("/v2") {
+        Some(base) => format!("{}/v3", base),
+        None => api_url().to_string(),
+    }
+}
+
+// Every request carries the user agent, the token is only sent to the Modrinth API and
+// never to the CDN or GitHub
+fn prepare(request: RequestBuilder, url: &str) -> RequestBuilder {
+    let request = request.header(USER_AGENT, &settings().user_agent);
+    match &settings().token {
+        Some(token) if url.starts_with(api_url()) || url.starts_with(&api_v3_url()) => request.header(AUTHORIZATION, token),
+        _ => request,
+    }
+}
+
+// The path is relative to the API's base url and the params are percent encoded
+fn api_endpoint(path: &str, params: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+    let url = format!("{}{}", api_url(), path);
+    if params.is_empty() {
+        return Ok(url);
+    }
+    Ok(reqwest::Url::parse_with_params(&url, params)?.to_string())
+}
+
+fn api_get(client: &Client, path: &str, params: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+    cached_get(&api_endpoint(path, params)?, client)
+}
+
+fn api_post(client: &Client, path: &str, body: &serde_json::Value) -> Result<String, Box<dyn Error>> {
+    let url = api_endpoint(path, &[])?;
+    explain("POST", &url);
+    Ok(send(prepare(client.post(&url), &url).json(body))?.text()?)
+}
+
+// The versions of the project for the loader and game version, with "any" game version
+// the filter is left out so every version of the loader is listed. A slug with a known id is
+// looked up by the id, the way dependencies are, so both share the memoized response
+fn get_version_list(project: &str, loader: &str, game_version: &str, client: &Client) -> Result<String, Box<dyn Error>> {
+    let project = cached_project_id(project).unwrap_or_else(|| project.to_string());
+    let loaders = serde_json::json!([loader]).to_string();
+    let game_versions = serde_json::json!([game_version]).to_string();
+    let mut params = Vec::new();
+    if !loader.is_empty() {
+        params.push(("loaders", loaders.as_str()));
+    }
+    if game_version != ANY_GAME_VERSION {
+        params.push(("game_versions", game_versions.as_str()));
+    }
+    api_get(client, &format!("/project/{}/version", project), &params)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Anything that would be rounded up to 1024.0 is shown in the next unit already
+    while size >= 1023.95 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn truncate(text: &str, length: usize) -> String {
+    if text.chars().count() <= length {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(length).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+fn remove_last_char(string: &str, char: char) -> String {
+    if let Some(index) = string.rfind(char) {
+        let mut result = String::with_capacity(string.len() - 1);
+        result.push_str(&string[..index]);
+        result.push_str(&string[index + char.len_utf8()..]);
+        result
+    } else {
+        string.to_string()
+    }
+}
+
+// The first candidate with an existing .minecraft folder wins, its mods folder is created if needed
+fn check_for_mods_dir() -> String{
+    static MODS_DIR: OnceLock<String> = OnceLock::new();
+    MODS_DIR.get_or_init(|| {
+        for minecraft_dir in minecraft_dir_candidates() {
+            if fs::metadata(&minecraft_dir).is_ok() {
+                let mods_dir = format!("{}/mods", minecraft_dir);
+                if fs::metadata(&mods_dir).is_err() {
+                    let _ = fs::create_dir(&mods_dir);
+                }
+                verbose(&format!("Using the mods folder at {}", mods_dir));
+                return mods_dir;
+            }
+            verbose(&format!("No .minecraft folder at {}", minecraft_dir));
+        }
+        "".to_string()
+    }).clone()
+}
+
+// Ordered by priority: the RUSTDRYNTH_MINECRAFT_DIR override, the standard install, then Flatpak and Snap
+fn minecraft_dir_candidates() -> Vec<String> {
+    let mut candidates = Vec::new();
+    if let Ok(custom) = env::var("RUSTDRYNTH_MINECRAFT_DIR") {
+        candidates.push(custom);
+    }
+    match whoami::platform() {
+        // APPDATA follows profiles moved to another drive, the guessed path is only a fallback
+        Platform::Windows => match env::var("APPDATA") {
+            Ok(appdata) => candidates.push(format!("{}/.minecraft", appdata.trim_end_matches(['/', '\\']))),
+            Err(_) => candidates.push(format!("C:/Users/{}/AppData/Roaming/.minecraft", whoami::username())),
+        },
+        Platform::Linux => {
+            if let Some(home) = dirs::home_dir() {
+                candidates.push(format!("{}/.minecraft", home.display()));
+                candidates.push(format!("{}/.var/app/com.mojang.Minecraft/.minecraft", home.display()));
+                candidates.push(format!("{}/snap/mc-installer/current/.minecraft", home.display()));
+            }
+        }
+        _ => {}
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(slug: &str, downloads: u64, follows: u64) -> Hit {
+        Hit {
+            project_id: slug.to_uppercase(),
+            project_type: "mod".to_string(),
+            slug: slug.to_string(),
+            title: slug.to_string(),
+            description: String::new(),
+            downloads,
+            follows,
+            date_created: String::new(),
+            date_modified: String::new(),
+        }
+    }
+
+    fn slugs(hits: &[Hit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.slug.as_str()).collect()
+    }
+
+    fn version(project_id: &str, required: &[&str]) -> GameVersion {
+        GameVersion {
+            project_id: project_id.to_string(),
+            version_number: "1.0.0".to_string(),
+            featured: false,
+            game_versions: Vec::new(),
+            loaders: Vec::new(),
+            files: Vec::new(),
+            dependencies: required.iter().map(|id| ProjectDependency {
+                project_id: Some(id.to_string()),
+                version_id: None,
+                file_name: None,
+                dependency_type: "required".to_string(),
+            }).collect(),
+        }
+    }
+
+    fn resolve(root: &GameVersion, lookup: &mut VersionLookup) -> Result<DependencyPlan, Box<dyn Error>> {
+        let mut plan = DependencyPlan { versions: Vec::new(), missing: Vec::new(), external: Vec::new(), saved: 0 };
+        let mut seen = HashSet::from([root.project_id.clone()]);
+        resolve_required_dependencies(root, &mut seen, &mut plan, lookup)?;
+        Ok(plan)
+    }
+
+    #[test]
+    fn plan_resolves_nested_and_shared_dependencies_once() {
+        let versions = HashMap::from([
+            ("a", version("a", &["c"])),
+            ("b", version("b", &["c"])),
+            ("c", version("c", &[])),
+        ]);
+        let mut lookups = Vec::new();
+        let plan = resolve(&version("root", &["a", "b"]), &mut |id| {
+            lookups.push(id.to_string());
+            Ok(versions[id].clone())
+        }).unwrap();
+        let resolved: Vec<&str> = plan.versions.iter().map(|version| version.project_id.as_str()).collect();
+        assert_eq!(resolved, ["a", "c", "b"]);
+        assert_eq!(lookups, ["a", "c", "b"]);
+        assert!(plan.missing.is_empty());
+    }
+
+    #[test]
+    fn plan_only_records_dependencies_without_a_version_as_missing() {
+        let plan = resolve(&version("root", &["gone"]), &mut |id| {
+            Err(Box::new(RustdrynthError::NotFound(format!("Nothing was found for {}", id))))
+        }).unwrap();
+        assert_eq!(plan.missing, [("root".to_string(), "gone".to_string())]);
+
+        let failed = resolve(&version("root", &["down"]), &mut |_| {
+            Err(Box::new(RustdrynthError::Network("the API answered 503 Service Unavailable".to_string())))
+        });
+        assert!(failed.is_err());
+    }
+
+    #[test]
+    fn plan_cache_key_depends_on_the_api_and_the_token() {
+        let options = DownloadOptions::plain("fabric", "1.20.1");
+        let version = version("sodium", &[]);
+        let modrinth = options.plan_cache_key(&version, &cache_scope("https://api.modrinth.com/v2", None)).unwrap();
+        let staging = options.plan_cache_key(&version, &cache_scope("https://staging-api.modrinth.com/v2", None)).unwrap();
+        let with_token = options.plan_cache_key(&version, &cache_scope("https://api.modrinth.com/v2", Some("mrp_secret"))).unwrap();
+        let other_token = options.plan_cache_key(&version, &cache_scope("https://api.modrinth.com/v2", Some("mrp_other"))).unwrap();
+        assert_eq!(HashSet::from([&modrinth, &staging, &with_token, &other_token]).len(), 4);
+        assert!(!with_token.contains("mrp_secret"));
+        assert_eq!(modrinth, options.plan_cache_key(&version, &cache_scope("https://api.modrinth.com/v2", None)).unwrap());
+    }
+
+    #[test]
+    fn plan_cache_key_is_only_made_for_plain_downloads() {
+        let mut options = DownloadOptions::plain("fabric", "1.20.1");
+        options.featured_only = true;
+        assert!(options.plan_cache_key(&version("sodium", &[]), "https://api.modrinth.com/v2").is_none());
+    }
+
+    #[test]
+    fn snapshot_versions_are_parsed_and_kept_as_they_are() {
+        let versions = |allow_snapshots| -> Vec<String> {
+            let tags: Vec<GameVersionTag> = serde_json::from_str(r#"[
+                {"version": "23w31a", "version_type": "snapshot", "date": "2023-08-01T12:00:00Z", "major": false},
+                {"version": "1.20.1", "version_type": "release", "date": "2023-06-12T13:25:51Z", "major": false},
+                {"version": "b1.7.3", "version_type": "beta", "date": "2011-07-08T00:00:00Z", "major": false}
+            ]"#).unwrap();
+            filter_game_versions(tags, allow_snapshots).into_iter().map(|tag| tag.version).collect()
+        };
+        assert_eq!(versions(false), ["1.20.1"]);
+        assert_eq!(versions(true), ["23w31a", "1.20.1"]);
+        // Passed as the game version of a command a snapshot is only trimmed
+        assert_eq!(normalize_game_version(" 23w31a ", false, &Client::new()), "23w31a");
+    }
+
+    // Answers one connection per response in order and hands back the requests it got, lowercased
+    fn serve(responses: Vec<Vec<u8>>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            responses.into_iter().map(|response| {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut byte = [0; 1];
+                while !request.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() > 0 {
+                    request.push(byte[0]);
+                }
+                stream.write_all(&response).unwrap();
+                String::from_utf8_lossy(&request).to_lowercase()
+            }).collect()
+        });
+        (url, handle)
+    }
+
+    fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        [head.into_bytes(), body.to_vec()].concat()
+    }
+
+    #[test]
+    fn gzipped_responses_are_decoded() {
+        // {"tag_name":"v9.9.9","html_url":"https://example.com"} gzipped
+        const GZIPPED: [u8; 71] = [
+            31, 139, 8, 0, 0, 0, 0, 0, 2, 255, 171, 86, 42, 73, 76, 143, 207, 75, 204, 77, 85, 178, 82, 42, 179, 212, 3, 66, 37, 29,
+            165, 140, 146, 220, 156, 248, 210, 162, 28, 160, 88, 70, 73, 73, 65, 177, 149, 190, 126, 106, 69, 98, 110, 65, 78, 170,
+            94, 114, 126, 174, 82, 45, 0, 104, 116, 32, 190, 54, 0, 0, 0,
+        ];
+        let (url, server) = serve(vec![response("200 OK", &[("Content-Type", "application/json"), ("Content-Encoding", "gzip")], &GZIPPED)]);
+        let resp_txt = cached_get(&format!("{}/gzipped", url), &build_client(None).unwrap()).unwrap();
+        let release: GithubRelease = serde_json::from_str(&resp_txt).unwrap();
+        assert_eq!(release.tag_name, "v9.9.9");
+        let requests = server.join().unwrap();
+        assert!(requests[0].lines().any(|line| line.starts_with("accept-encoding:") && line.contains("gzip")));
+    }
+
+    fn redirect(location: &str) -> Vec<u8> {
+        response("302 Found", &[("Location", location)], b"")
+    }
+
+    #[test]
+    fn redirects_past_the_limit_are_an_error() {
+        let client = build_client(None).unwrap();
+        let mut responses: Vec<Vec<u8>> = (1..MAX_REDIRECTS).map(|hop| redirect(&format!("/{}", hop))).collect();
+        responses.push(response("200 OK", &[], b"done"));
+        let (url, server) = serve(responses);
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "done");
+        assert_eq!(server.join().unwrap().len(), MAX_REDIRECTS);
+
+        let (url, server) = serve((1..=MAX_REDIRECTS).map(|hop| redirect(&format!("/{}", hop))).collect());
+        assert!(client.get(&url).send().unwrap_err().is_redirect());
+        assert_eq!(server.join().unwrap().len(), MAX_REDIRECTS);
+    }
+
+    #[test]
+    fn files_are_fetched_through_redirects_but_never_html() {
+        let client = build_client(None).unwrap();
+        let part = env::temp_dir().join(format!("rustdrynth-test-{}.jar.part", process::id()));
+        let fetch = |last: Vec<u8>| {
+            let (url, _) = serve(vec![redirect("/cdn"), redirect("/cdn/file.jar"), last]);
+            let game_file = GameFiles { url, filename: "file.jar".to_string(), size: 7, hashes: HashMap::new(), primary: true };
+            fetch_part(&game_file, &part, &client)
+        };
+        let page = fetch(response("200 OK", &[("Content-Type", "text/html; charset=utf-8")], b"<html>"));
+        assert!(matches!(page, Ok(None)));
+        let jar = fetch(response("200 OK", &[("Content-Type", "application/java-archive")], b"PK\x03\x04jar"));
+        assert!(matches!(jar, Ok(Some(7))));
+        assert_eq!(fs::read(&part).unwrap(), b"PK\x03\x04jar");
+        fs::remove_file(&part).unwrap();
+    }
+
+    #[test]
+    fn old_jars_are_never_overwritten() {
+        let old_dir = env::temp_dir().join(format!("rustdrynth-test-old-{}", process::id()));
+        fs::create_dir_all(&old_dir).unwrap();
+        let jar = Path::new("mods/sodium.jar");
+        assert_eq!(free_old_path(&old_dir, jar), old_dir.join("sodium.jar"));
+        fs::write(old_dir.join("sodium.jar"), b"").unwrap();
+        assert_eq!(free_old_path(&old_dir, jar), old_dir.join("sodium-1.jar"));
+        fs::write(old_dir.join("sodium-1.jar"), b"").unwrap();
+        assert_eq!(free_old_path(&old_dir, jar), old_dir.join("sodium-2.jar"));
+        fs::remove_dir_all(&old_dir).unwrap();
+    }
+
+    #[test]
+    fn format_bytes_switches_units_at_1024() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(1024 * 1024 - 1), "1.0 MiB");
+        assert_eq!(format_bytes(1024 * 1024), "1.0 MiB");
+        assert_eq!(format_bytes(1024 * 1024 * 1024 - 1), "1.0 GiB");
+        assert_eq!(format_bytes(1024 * 1024 * 1024), "1.0 GiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024 * 1024), "5120.0 GiB");
+    }
+
+    #[test]
+    fn secondary_sort_is_rejected_with_relevance() {
+        assert!(check_secondary_sort(SearchSort::Relevance, Some(SearchSort::Downloads)).is_err());
+        assert!(check_secondary_sort(SearchSort::Relevance, None).is_ok());
+        assert!(check_secondary_sort(SearchSort::Follows, Some(SearchSort::Downloads)).is_ok());
+    }
+
+    #[test]
+    fn secondary_sort_breaks_ties_of_the_primary_one() {
+        let mut hits = vec![hit("a", 10, 1), hit("b", 20, 0), hit("c", 10, 5)];
+        sort_hits(&mut hits, SearchSort::Downloads, SearchSort::Follows);
+        assert_eq!(slugs(&hits), ["b", "c", "a"]);
+    }
 }